@@ -2,7 +2,11 @@ use bevy::prelude::*;
 
 use crate::{asset_tracking::LoadResource, audio::music, gameplay::player, screens::Screen};
 
+pub mod town_grid;
+
 pub(super) fn plugin(app: &mut App) {
+    app.add_plugins(town_grid::plugin);
+
     app.register_type::<LevelAssets>();
     app.load_resource::<LevelAssets>();
 }
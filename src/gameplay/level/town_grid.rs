@@ -0,0 +1,201 @@
+//! The grid of tiles the town is built on, and which building stands on each tile.
+
+use bevy::prelude::*;
+use thiserror::Error;
+
+use crate::{gameplay::models::suburban::BuildingId, screens::Screen};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<TownGrid>();
+
+    app.add_observer(free_tiles);
+    app.add_systems(OnExit(Screen::Gameplay), clear_town_grid);
+}
+
+/// How many tiles the town spans along X and Z.
+const TOWN_SIZE: UVec2 = UVec2::splat(32);
+/// The width of a tile in world units.
+const TILE_SIZE: f32 = 0.5;
+
+/// A rectangle of tiles, such as the footprint of a building on the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileRect {
+    /// The tile with the lowest coordinates.
+    pub min: IVec2,
+    /// How many tiles the rectangle spans along X and Z.
+    pub size: UVec2,
+}
+
+impl TileRect {
+    /// The tile past the last one of the rectangle, on both axes.
+    pub fn max(&self) -> IVec2 {
+        self.min + self.size.as_ivec2()
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = IVec2> {
+        let (min, max) = (self.min, self.max());
+        (min.y..max.y).flat_map(move |y| (min.x..max.x).map(move |x| IVec2::new(x, y)))
+    }
+}
+
+/// Why a building can't be placed on the grid.
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementError {
+    #[error("It doesn't fit in the town.")]
+    OutOfBounds,
+    #[error("It overlaps with {occupant}.")]
+    Overlaps { occupant: Entity },
+}
+
+/// The tiles of the town, centered on the origin, and the building standing on each of them.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct TownGrid {
+    size: UVec2,
+    tile_size: f32,
+    /// The building on each tile, row by row along Z.
+    tiles: Vec<Option<Entity>>,
+}
+
+impl Default for TownGrid {
+    fn default() -> Self {
+        Self::new(TOWN_SIZE, TILE_SIZE)
+    }
+}
+
+impl TownGrid {
+    pub fn new(size: UVec2, tile_size: f32) -> Self {
+        Self {
+            size,
+            tile_size,
+            tiles: vec![None; size.element_product() as usize],
+        }
+    }
+
+    pub fn tile_size(&self) -> f32 {
+        self.tile_size
+    }
+
+    /// The world position of the corner of tile `(0, 0)`, on the XZ plane.
+    fn origin(&self) -> Vec2 {
+        -self.size.as_vec2() * self.tile_size / 2.0
+    }
+
+    fn index(&self, tile: IVec2) -> Option<usize> {
+        let in_bounds = tile.cmpge(IVec2::ZERO).all() && tile.cmplt(self.size.as_ivec2()).all();
+        in_bounds.then(|| (tile.y * self.size.x as i32 + tile.x) as usize)
+    }
+
+    /// The building standing on the given tile, if any.
+    pub fn occupant(&self, tile: IVec2) -> Option<Entity> {
+        self.tiles[self.index(tile)?]
+    }
+
+    /// The tiles a footprint of the given size covers when centered as close as it gets
+    /// to `point`. The footprint may leave the grid.
+    pub fn snap(&self, point: Vec3, footprint: UVec2) -> TileRect {
+        let tile = (point.xz() - self.origin()) / self.tile_size;
+        TileRect {
+            min: (tile - footprint.as_vec2() / 2.0).round().as_ivec2(),
+            size: footprint,
+        }
+    }
+
+    /// The world position of the center of the given tiles, on the ground.
+    pub fn center(&self, rect: TileRect) -> Vec3 {
+        let center =
+            self.origin() + (rect.min.as_vec2() + rect.size.as_vec2() / 2.0) * self.tile_size;
+        Vec3::new(center.x, 0.0, center.y)
+    }
+
+    /// Checks that a building could be placed on the given tiles.
+    pub fn check(&self, rect: TileRect) -> Result<(), PlacementError> {
+        let mut occupant = None;
+        for tile in rect.tiles() {
+            let index = self.index(tile).ok_or(PlacementError::OutOfBounds)?;
+            occupant = occupant.or(self.tiles[index]);
+        }
+        match occupant {
+            Some(occupant) => Err(PlacementError::Overlaps { occupant }),
+            None => Ok(()),
+        }
+    }
+
+    /// Marks the given tiles as taken by a building, if they are all free.
+    pub fn occupy(&mut self, rect: TileRect, building: Entity) -> Result<(), PlacementError> {
+        self.check(rect)?;
+        for tile in rect.tiles() {
+            if let Some(index) = self.index(tile) {
+                self.tiles[index] = Some(building);
+            }
+        }
+        Ok(())
+    }
+
+    /// Frees every tile taken by the given building.
+    pub fn free(&mut self, building: Entity) {
+        for tile in &mut self.tiles {
+            if *tile == Some(building) {
+                *tile = None;
+            }
+        }
+    }
+}
+
+/// Frees the tiles of a building when it is removed
+fn free_tiles(trigger: Trigger<OnRemove, BuildingId>, mut grid: ResMut<TownGrid>) {
+    grid.free(trigger.target());
+}
+
+/// Empties the town, whose buildings are despawned with the gameplay screen
+fn clear_town_grid(mut grid: ResMut<TownGrid>) {
+    *grid = TownGrid::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn footprints_snap_to_tiles() {
+        let grid = TownGrid::new(UVec2::splat(4), 1.0);
+        // An odd footprint is centered on the tile under the point.
+        let rect = grid.snap(Vec3::new(0.3, 0.0, -0.8), UVec2::ONE);
+        assert_eq!(rect.min, IVec2::new(2, 1));
+        assert_eq!(grid.center(rect), Vec3::new(0.5, 0.0, -0.5));
+
+        // An even one is centered on the nearest tile corner.
+        let rect = grid.snap(Vec3::new(0.3, 0.0, -0.8), UVec2::new(2, 2));
+        assert_eq!(rect.min, IVec2::new(1, 0));
+        assert_eq!(grid.center(rect), Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn buildings_may_not_overlap_or_leave_the_town() {
+        let mut grid = TownGrid::new(UVec2::splat(4), 1.0);
+        let house = Entity::from_raw(1);
+        let house_tiles = TileRect {
+            min: IVec2::ZERO,
+            size: UVec2::new(2, 3),
+        };
+        assert_eq!(grid.occupy(house_tiles, house), Ok(()));
+        assert_eq!(grid.occupant(IVec2::new(1, 2)), Some(house));
+        assert_eq!(grid.occupant(IVec2::new(2, 2)), None);
+
+        let overlapping = TileRect {
+            min: IVec2::new(1, 2),
+            size: UVec2::new(2, 2),
+        };
+        assert_eq!(
+            grid.occupy(overlapping, Entity::from_raw(2)),
+            Err(PlacementError::Overlaps { occupant: house })
+        );
+        let outside = TileRect {
+            min: IVec2::new(3, 0),
+            size: UVec2::new(2, 1),
+        };
+        assert_eq!(grid.check(outside), Err(PlacementError::OutOfBounds));
+
+        grid.free(house);
+        assert_eq!(grid.check(overlapping), Ok(()));
+    }
+}
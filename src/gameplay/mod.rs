@@ -1,7 +1,8 @@
-use avian3d::PhysicsPlugins;
+use avian3d::{PhysicsPlugins, prelude::*};
 use bevy::prelude::*;
 
 use crate::{
+    Pause,
     gameplay::{
         cursors::{init_cursor_icons, set_cursors},
         level::spawn_level,
@@ -15,14 +16,30 @@ mod level;
 mod models;
 mod player;
 
+pub use player::replay_roll_file;
+
 pub(crate) fn plugin(app: &mut App) {
     app.add_plugins((
         player::plugin,
         level::plugin,
         models::plugin,
         PhysicsPlugins::default(),
+        // Lets the player click on meshes in the world, such as dice.
+        MeshPickingPlugin,
     ));
     app.add_systems(Startup, (init_cursor_icons, set_cursors).chain());
     app.add_systems(OnEnter(Screen::Gameplay), spawn_level);
     app.add_systems(OnExit(Screen::Gameplay), teardown_player);
+
+    // Freeze physics behind the pause overlay.
+    app.add_systems(OnEnter(Pause(true)), pause_physics);
+    app.add_systems(OnExit(Pause(true)), unpause_physics);
+}
+
+fn pause_physics(mut time: ResMut<Time<Physics>>) {
+    time.pause();
+}
+
+fn unpause_physics(mut time: ResMut<Time<Physics>>) {
+    time.unpause();
 }
@@ -0,0 +1,201 @@
+//! The size of models, such as buildings, measured from their meshes once they have loaded.
+
+use bevy::{
+    gltf::{Gltf, GltfMesh, GltfNode},
+    platform::collections::HashSet,
+    prelude::*,
+    render::mesh::VertexAttributeValues,
+};
+
+/// A model may stick out of its footprint by this fraction of a tile, so that a sliver of
+/// wall or roof doesn't take a whole extra row of tiles.
+const FOOTPRINT_TOLERANCE: f32 = 0.1;
+
+/// The axis-aligned box around a building model, and the tiles of the town grid it covers.
+#[derive(Reflect, Debug, Clone, Copy, PartialEq)]
+pub struct BuildingBounds {
+    pub min: Vec3,
+    pub max: Vec3,
+    /// How many tiles the building covers along X and Z.
+    pub footprint: UVec2,
+}
+
+impl BuildingBounds {
+    /// Measures the meshes of every node of the model, in the space of the model.
+    /// Returns `None` if any of its assets hasn't loaded, or if it has no meshes.
+    pub fn measure(
+        gltf: &Gltf,
+        nodes: &Assets<GltfNode>,
+        gltf_meshes: &Assets<GltfMesh>,
+        meshes: &Assets<Mesh>,
+        tile_size: f32,
+    ) -> Option<Self> {
+        let (min, max) = measure_model(gltf, nodes, gltf_meshes, meshes)?;
+        Some(Self::new(min, max, tile_size))
+    }
+
+    /// The bounds of a box, covering the tiles it fills up to [`FOOTPRINT_TOLERANCE`].
+    pub fn new(min: Vec3, max: Vec3, tile_size: f32) -> Self {
+        let tiles = (max - min).xz() / tile_size - FOOTPRINT_TOLERANCE;
+        Self {
+            min,
+            max,
+            footprint: tiles.ceil().max(Vec2::ONE).as_uvec2(),
+        }
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn height(&self) -> f32 {
+        self.max.y - self.min.y
+    }
+
+    /// The center of the box, relative to the origin of the model.
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) / 2.0
+    }
+}
+
+/// The corners of the axis-aligned box around the meshes of every node of a model, in the
+/// space of the model. Returns `None` if any of its assets hasn't loaded, or if it has no
+/// meshes.
+pub fn measure_model(
+    gltf: &Gltf,
+    nodes: &Assets<GltfNode>,
+    gltf_meshes: &Assets<GltfMesh>,
+    meshes: &Assets<Mesh>,
+) -> Option<(Vec3, Vec3)> {
+    let gltf_nodes = gltf
+        .nodes
+        .iter()
+        .map(|handle| nodes.get(handle))
+        .collect::<Option<Vec<_>>>()?;
+    let children: HashSet<AssetId<GltfNode>> = gltf_nodes
+        .iter()
+        .flat_map(|node| node.children.iter().map(Handle::id))
+        .collect();
+
+    let mut bounds = (Vec3::INFINITY, Vec3::NEG_INFINITY);
+    let mut stack: Vec<_> = gltf
+        .nodes
+        .iter()
+        .zip(gltf_nodes)
+        .filter(|(handle, _)| !children.contains(&handle.id()))
+        .map(|(_, node)| (node, node.transform.compute_affine()))
+        .collect();
+    while let Some((node, transform)) = stack.pop() {
+        if let Some(mesh) = &node.mesh {
+            for primitive in &gltf_meshes.get(mesh)?.primitives {
+                let positions = meshes
+                    .get(&primitive.mesh)?
+                    .attribute(Mesh::ATTRIBUTE_POSITION)
+                    .and_then(VertexAttributeValues::as_float3)
+                    .unwrap_or_default();
+                for &position in positions {
+                    let position = transform.transform_point3(position.into());
+                    bounds = (bounds.0.min(position), bounds.1.max(position));
+                }
+            }
+        }
+        for child in &node.children {
+            let child = nodes.get(child)?;
+            stack.push((child, transform * child.transform.compute_affine()));
+        }
+    }
+
+    let (min, max) = bounds;
+    min.cmple(max).all().then_some((min, max))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, thread, time::Duration};
+
+    use bevy::{asset::LoadState, gltf::GltfPlugin};
+
+    use super::*;
+
+    #[test]
+    fn footprints_round_to_whole_tiles() {
+        let fence = BuildingBounds::new(
+            Vec3::new(-0.64, 0.0, -0.6),
+            Vec3::new(0.64, 0.27, 0.64),
+            0.5,
+        );
+        assert_eq!(fence.footprint, UVec2::new(3, 3));
+        let house = BuildingBounds::new(
+            Vec3::new(-0.65, 0.0, -0.51),
+            Vec3::new(0.65, 0.8, 0.51),
+            0.5,
+        );
+        assert_eq!(house.footprint, UVec2::new(3, 2));
+        let tree = BuildingBounds::new(Vec3::splat(-0.1), Vec3::splat(0.1), 0.5);
+        assert_eq!(tree.footprint, UVec2::ONE);
+    }
+
+    #[test]
+    fn every_building_model_has_bounds() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            GltfPlugin::default(),
+        ));
+        app.init_asset::<Mesh>();
+        app.init_asset::<StandardMaterial>();
+        app.init_asset::<Image>();
+        app.init_asset::<Scene>();
+        app.finish();
+        app.cleanup();
+
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/models/buildings");
+        let models: Vec<(String, Handle<Gltf>)> = fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(".glb"))
+            .map(|name| {
+                let handle = app
+                    .world()
+                    .resource::<AssetServer>()
+                    .load(format!("models/buildings/{name}"));
+                (name, handle)
+            })
+            .collect();
+        assert!(!models.is_empty());
+
+        for _ in 0..1000 {
+            app.update();
+            let assets = app.world().resource::<AssetServer>();
+            if models
+                .iter()
+                .all(|(_, handle)| !matches!(assets.load_state(handle), LoadState::Loading))
+            {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let world = app.world();
+        for (name, handle) in &models {
+            let gltf = world
+                .resource::<Assets<Gltf>>()
+                .get(handle)
+                .unwrap_or_else(|| panic!("{name} didn't load"));
+            let bounds = BuildingBounds::measure(
+                gltf,
+                world.resource(),
+                world.resource(),
+                world.resource(),
+                0.5,
+            )
+            .unwrap_or_else(|| panic!("{name} has no bounds"));
+            assert!(bounds.height() > 0.0, "{name}: {bounds:?}");
+            assert!(
+                bounds.footprint.cmpge(UVec2::ONE).all(),
+                "{name}: {bounds:?}"
+            );
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use bevy::prelude::*;
+pub mod bounds;
 pub mod dice;
+pub mod suburban;
 
 pub(super) fn plugin(app: &mut App) {
-    app.add_plugins(dice::plugin);
+    app.add_plugins((dice::plugin, suburban::plugin));
 }
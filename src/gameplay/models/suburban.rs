@@ -1,99 +1,220 @@
-use bevy::prelude::*;
+//! The catalog of buildings that can be placed in the town, loaded from
+//! `assets/buildings.catalog.ron` so that buildings can be added without recompiling.
+
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{
+    asset::{
+        AssetLoader, AssetPath, LoadContext,
+        io::{AssetReaderError, Reader},
+    },
+    gltf::{GltfMesh, GltfNode},
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
+use serde::Deserialize;
 use thiserror::Error;
 
-use crate::asset_tracking::LoadResource;
+use super::bounds::BuildingBounds;
+use crate::{asset_tracking::LoadResource, gameplay::level::town_grid::TownGrid};
 
 pub(crate) fn plugin(app: &mut App) {
-    app.register_type::<SuburbanBuildings>();
-    app.load_resource::<SuburbanBuildings>();
-    app.register_type::<BuildingHandle>();
+    app.register_type::<BuildingId>();
+    app.register_type::<BuildingOrientation>();
+    app.register_type::<BuildingCatalog>();
+    app.init_asset::<BuildingCatalog>();
+    app.init_asset_loader::<BuildingCatalogLoader>();
+    app.init_resource::<BuildingCatalog>();
+    app.load_resource::<BuildingCatalogAssets>();
+
+    // Before the gameplay screen may be entered in the same frame, so that it sees the
+    // buildings as soon as they are loaded.
+    app.add_systems(PreUpdate, apply_building_catalog);
+    app.add_systems(Update, measure_buildings);
 }
 
-#[derive(Component, Reflect, Clone, Debug, PartialEq, Eq)]
+/// Which building of the catalog a placed building is.
+#[derive(Component, Reflect, Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
 #[reflect(Component)]
-pub enum BuildingType {
-    Residential(ResidentialType),
-    Driveway(DrivewayType),
-    Fence(FenceType),
-    Path(PathType),
+#[serde(transparent)]
+pub struct BuildingId(pub String);
+
+/// How a building is turned and mirrored.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct BuildingOrientation {
+    /// Quarter turns around Y, from 0 to 3.
+    pub quarter_turns: u8,
+    /// Whether the model is mirrored along its X axis, before being turned.
+    pub mirrored: bool,
+}
+
+impl BuildingOrientation {
+    /// The orientation turned by the given number of quarter turns, which may be negative.
+    pub fn turned(self, quarter_turns: i32) -> Self {
+        Self {
+            quarter_turns: (self.quarter_turns as i32 + quarter_turns).rem_euclid(4) as u8,
+            ..self
+        }
+    }
+
+    /// The orientation mirrored, or no longer mirrored.
+    pub fn flipped(self) -> Self {
+        Self {
+            mirrored: !self.mirrored,
+            ..self
+        }
+    }
+
+    pub fn rotation(&self) -> Quat {
+        Quat::from_rotation_y(FRAC_PI_2 * self.quarter_turns as f32)
+    }
+
+    pub fn scale(&self) -> Vec3 {
+        if self.mirrored {
+            Vec3::new(-1.0, 1.0, 1.0)
+        } else {
+            Vec3::ONE
+        }
+    }
+
+    /// Where a point of the model ends up, relative to the origin of the building.
+    pub fn apply(&self, point: Vec3) -> Vec3 {
+        self.rotation() * (self.scale() * point)
+    }
+
+    /// The footprint of a building with this orientation, given its unturned footprint.
+    pub fn footprint(&self, footprint: UVec2) -> UVec2 {
+        if self.quarter_turns % 2 == 1 {
+            footprint.yx()
+        } else {
+            footprint
+        }
+    }
+}
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum BuildingCategory {
+    Residential,
+    Driveway,
+    Fence,
+    Path,
     Planter,
-    Tree(TreeType),
+    Tree,
+}
+
+/// A building of the catalog.
+#[derive(Reflect, Clone, Debug, PartialEq)]
+pub struct Building {
+    pub id: BuildingId,
+    /// The name shown to the player.
+    pub name: String,
+    pub category: BuildingCategory,
+    pub model: Handle<Gltf>,
+    /// How many tiles the building covers along X and Z, if not measured from the model.
+    pub footprint: Option<UVec2>,
+    pub cost: u32,
+    pub tags: Vec<String>,
+    /// Measured from the model once it has loaded.
+    bounds: Option<BuildingBounds>,
+}
+
+impl Building {
+    /// The size of the model, once it has loaded.
+    pub fn bounds(&self) -> Option<BuildingBounds> {
+        self.bounds
+    }
 }
 
-pub fn get_handle_from_building_type(
-    ty: &BuildingType,
-    buildings: &SuburbanBuildings,
-) -> Option<Handle<Gltf>> {
-    buildings.get(ty).map(|handle| handle.handle().clone_weak())
+/// Every building that can be placed, in the order they are listed in.
+#[derive(Resource, Asset, Reflect, Clone, Debug, Default)]
+#[reflect(Resource)]
+pub struct BuildingCatalog {
+    buildings: Vec<Building>,
+    /// The position of each building in `buildings`.
+    index: HashMap<BuildingId, usize>,
+}
+
+impl BuildingCatalog {
+    pub fn get(&self, id: &BuildingId) -> Option<&Building> {
+        self.buildings.get(*self.index.get(id)?)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Building> {
+        self.buildings.iter()
+    }
 }
 
+/// The building the player has picked and is about to place.
 #[derive(Resource, Clone, Debug)]
 pub struct CurrentBuilding {
-    ty: BuildingType,
+    id: BuildingId,
+    name: String,
     model: Handle<Gltf>,
     scene: Handle<Scene>,
-    mat_id: AssetId<StandardMaterial>,
+    bounds: BuildingBounds,
+    orientation: BuildingOrientation,
 }
 
 #[derive(Error, Debug)]
 pub enum CurrentBuildingError {
-    #[error("Building type {ty:?} does not have a valid handle")]
-    Handle { ty: BuildingType },
-    #[error("Invalid asset. Could not retrieve {ty:?} asset with id {id:?}")]
-    GltfAsset { ty: BuildingType, id: AssetId<Gltf> },
+    #[error("Building {id:?} is not in the catalog")]
+    NotInCatalog { id: BuildingId },
+    #[error("Invalid asset. Could not retrieve {id:?} asset with id {asset:?}")]
+    GltfAsset {
+        id: BuildingId,
+        asset: AssetId<Gltf>,
+    },
     #[error("Gltf asset {model:?} has no scenes!")]
     Scenes { model: Handle<Gltf> },
-    #[error("Gltf asset {model:?} has no materials!")]
-    Materials { model: Handle<Gltf> },
+    #[error("Building {id:?} hasn't been measured yet")]
+    Bounds { id: BuildingId },
 }
 
 impl CurrentBuilding {
     pub fn new(
-        ty: &BuildingType,
-        buildings: &SuburbanBuildings,
+        id: &BuildingId,
+        catalog: &BuildingCatalog,
         gltf: &Assets<Gltf>,
-        materials: &mut Assets<StandardMaterial>,
     ) -> Result<Self, CurrentBuildingError> {
-        let Some(handle) = get_handle_from_building_type(ty, buildings) else {
-            return Err(CurrentBuildingError::Handle { ty: ty.clone() });
+        let Some(building) = catalog.get(id) else {
+            return Err(CurrentBuildingError::NotInCatalog { id: id.clone() });
         };
 
-        let id = handle.id();
-        let Some(model) = gltf.get(id) else {
-            return Err(CurrentBuildingError::GltfAsset { ty: ty.clone(), id });
+        let asset = building.model.id();
+        let Some(model) = gltf.get(asset) else {
+            return Err(CurrentBuildingError::GltfAsset {
+                id: id.clone(),
+                asset,
+            });
         };
 
         let Some(scene) = model.scenes.first() else {
             return Err(CurrentBuildingError::Scenes {
-                model: handle.clone_weak(),
-            });
-        };
-
-        let Some(mat) = model.materials.first() else {
-            return Err(CurrentBuildingError::Materials {
-                model: handle.clone_weak(),
+                model: building.model.clone_weak(),
             });
         };
 
-        let mat_id = mat.id();
-        if let Some(mat_asset) = materials.get_mut(mat_id) {
-            mat_asset.diffuse_transmission = 0.4;
-        } else {
-            warn!(
-                "Invalid material asset. Could not retrieve {ty:?}'s material with id {mat_id:?}"
-            );
+        let Some(bounds) = building.bounds() else {
+            return Err(CurrentBuildingError::Bounds { id: id.clone() });
         };
 
         Ok(Self {
-            ty: ty.clone(),             // clone if it's `Clone`
-            model: handle.clone_weak(), // store the handle
-            scene: scene.clone_weak(),  // store the handle
-            mat_id,
+            id: id.clone(),
+            name: building.name.clone(),
+            model: building.model.clone_weak(),
+            scene: scene.clone_weak(),
+            bounds,
+            orientation: BuildingOrientation::default(),
         })
     }
 
-    pub fn ty(&self) -> &BuildingType {
-        &self.ty
+    pub fn id(&self) -> &BuildingId {
+        &self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
     }
 
     pub fn model(&self) -> &Handle<Gltf> {
@@ -104,390 +225,269 @@ impl CurrentBuilding {
         &self.scene
     }
 
-    pub fn mat_id(&self) -> AssetId<StandardMaterial> {
-        self.mat_id
+    pub fn bounds(&self) -> &BuildingBounds {
+        &self.bounds
+    }
+
+    pub fn orientation(&self) -> BuildingOrientation {
+        self.orientation
+    }
+
+    pub fn set_orientation(&mut self, orientation: BuildingOrientation) {
+        self.orientation = orientation;
+    }
+
+    /// The tiles the building covers along X and Z, as it is turned.
+    pub fn footprint(&self) -> UVec2 {
+        self.orientation.footprint(self.bounds.footprint)
     }
 }
 
-#[derive(Reflect, Clone, Debug, PartialEq, Eq)]
-pub enum ResidentialType {
-    A,
-    B,
-    C,
-    D,
-    E,
-    F,
-    G,
-    H,
-    I,
-    J,
-    K,
-    L,
-    M,
-    N,
-    O,
-    P,
-    Q,
-    R,
-    S,
-    T,
-    U,
+/// A catalog as written in a `.catalog.ron` file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct BuildingCatalogFile {
+    buildings: Vec<BuildingFile>,
 }
 
-#[derive(Reflect, Clone, Debug, PartialEq, Eq)]
-pub enum DrivewayType {
-    Long,
-    Short,
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct BuildingFile {
+    id: BuildingId,
+    name: String,
+    category: BuildingCategory,
+    /// Asset path of the model.
+    model: String,
+    #[serde(default)]
+    footprint: Option<(u32, u32)>,
+    #[serde(default)]
+    cost: u32,
+    #[serde(default)]
+    tags: Vec<String>,
 }
 
-#[derive(Reflect, Clone, Debug, PartialEq, Eq)]
-pub enum FenceType {
-    OneByTwo,
-    OneByThree,
-    OneByFour,
-    TwoByTwo,
-    TwoByThree,
-    ThreeByTwo,
-    ThreeByThree,
-    Low,
-    Regular,
+impl BuildingCatalogFile {
+    /// Checks that no two buildings share an id.
+    fn validate(&self) -> Result<(), BuildingCatalogError> {
+        let mut ids = HashSet::new();
+        for building in &self.buildings {
+            if !ids.insert(&building.id) {
+                return Err(BuildingCatalogError::DuplicateId(building.id.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Resource, Asset, Clone, Reflect)]
+#[reflect(Resource)]
+struct BuildingCatalogAssets {
+    #[dependency]
+    catalog: Handle<BuildingCatalog>,
 }
 
-#[derive(Reflect, Clone, Debug, PartialEq, Eq)]
-pub enum PathType {
-    Long,
-    Short,
-    StonesLong,
-    StonesMessy,
-    StonesShort,
+impl FromWorld for BuildingCatalogAssets {
+    fn from_world(world: &mut World) -> Self {
+        let assets = world.resource::<AssetServer>();
+        Self {
+            catalog: assets.load("buildings.catalog.ron"),
+        }
+    }
 }
 
-#[derive(Reflect, Clone, Debug, PartialEq, Eq)]
-pub enum TreeType {
-    Large,
-    Small,
+#[derive(Debug, Error)]
+enum BuildingCatalogError {
+    #[error("Couldn't read the building catalog: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Couldn't parse the building catalog: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("More than one building has the id {0:?}")]
+    DuplicateId(BuildingId),
+    #[error("Couldn't find the model of building {id:?}: {source}")]
+    MissingModel {
+        id: BuildingId,
+        source: AssetReaderError,
+    },
 }
 
-#[derive(Asset, Reflect, Clone, Debug, PartialEq, Eq)]
-pub struct BuildingHandle {
-    name: String,
-    handle: Handle<Gltf>,
+struct BuildingCatalogLoader {
+    /// To check that the models exist, which a [`LoadContext`] can't do without reading them.
+    assets: AssetServer,
 }
 
-impl BuildingHandle {
-    pub fn new(name: &str, handle: Handle<Gltf>) -> Self {
+impl FromWorld for BuildingCatalogLoader {
+    fn from_world(world: &mut World) -> Self {
         Self {
-            name: name.to_string(),
-            handle,
+            assets: world.resource::<AssetServer>().clone(),
         }
     }
+}
 
-    pub fn name(&self) -> &str {
-        &self.name
+impl BuildingCatalogLoader {
+    /// Opens the model at the given path without reading it, to know whether it exists.
+    async fn check_model(&self, path: &str) -> Result<(), AssetReaderError> {
+        let path = AssetPath::parse(path);
+        let source = self
+            .assets
+            .get_source(path.source())
+            .map_err(|_| AssetReaderError::NotFound(path.path().to_path_buf()))?;
+        source.reader().read(path.path()).await?;
+        Ok(())
     }
+}
 
-    pub fn handle(&self) -> &Handle<Gltf> {
-        &self.handle
+impl AssetLoader for BuildingCatalogLoader {
+    type Asset = BuildingCatalog;
+    type Settings = ();
+    type Error = BuildingCatalogError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: BuildingCatalogFile = ron::de::from_bytes(&bytes)?;
+        file.validate()?;
+
+        let mut catalog = BuildingCatalog::default();
+        for building in file.buildings {
+            // Fail here rather than leaving the catalog waiting on a model that never loads.
+            if let Err(source) = self.check_model(&building.model).await {
+                return Err(BuildingCatalogError::MissingModel {
+                    id: building.id,
+                    source,
+                });
+            }
+            catalog
+                .index
+                .insert(building.id.clone(), catalog.buildings.len());
+            catalog.buildings.push(Building {
+                id: building.id,
+                name: building.name,
+                category: building.category,
+                model: load_context.load(building.model),
+                footprint: building.footprint.map(UVec2::from),
+                cost: building.cost,
+                tags: building.tags,
+                bounds: None,
+            });
+        }
+        Ok(catalog)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["catalog.ron"]
     }
 }
 
-#[derive(Resource, Asset, Clone, Reflect)]
-#[reflect(Resource)]
-pub struct SuburbanBuildings {
-    pub buildings: Vec<(BuildingType, BuildingHandle)>,
+/// Copies the catalog into its resource once it is loaded, and again whenever the file
+/// changes while hot reloading.
+fn apply_building_catalog(
+    mut events: EventReader<AssetEvent<BuildingCatalog>>,
+    catalogs: Res<Assets<BuildingCatalog>>,
+    mut catalog: ResMut<BuildingCatalog>,
+) {
+    for event in events.read() {
+        let id = match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => *id,
+            _ => continue,
+        };
+        if let Some(loaded) = catalogs.get(id) {
+            info!("Loaded {} buildings.", loaded.buildings.len());
+            *catalog = loaded.clone();
+        }
+    }
 }
 
-impl SuburbanBuildings {
-    pub fn get(&self, building_type: &BuildingType) -> Option<&BuildingHandle> {
-        self.buildings.iter().find_map(|(btype, handle)| {
-            if btype == building_type {
-                Some(handle)
-            } else {
-                None
-            }
-        })
+/// Measures the models of the buildings whenever the catalog or a model finishes loading
+fn measure_buildings(
+    mut events: EventReader<AssetEvent<Gltf>>,
+    mut catalog: ResMut<BuildingCatalog>,
+    grid: Res<TownGrid>,
+    gltf: Res<Assets<Gltf>>,
+    nodes: Res<Assets<GltfNode>>,
+    gltf_meshes: Res<Assets<GltfMesh>>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    let loaded = events
+        .read()
+        .any(|event| matches!(event, AssetEvent::LoadedWithDependencies { .. }));
+    if !loaded && !catalog.is_changed() {
+        return;
+    }
+    for building in &mut catalog.buildings {
+        if building.bounds.is_some() {
+            continue;
+        }
+        building.bounds = gltf
+            .get(&building.model)
+            .and_then(|model| {
+                BuildingBounds::measure(model, &nodes, &gltf_meshes, &meshes, grid.tile_size())
+            })
+            .map(|bounds| BuildingBounds {
+                footprint: building.footprint.unwrap_or(bounds.footprint),
+                ..bounds
+            });
     }
 }
 
-impl FromWorld for SuburbanBuildings {
-    fn from_world(world: &mut World) -> Self {
-        let asset_server = world.resource::<AssetServer>();
-        let buildings = vec![
-            (
-                BuildingType::Residential(ResidentialType::A),
-                BuildingHandle::new(
-                    "building-type-a",
-                    asset_server.load("models/buildings/building-type-a.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::B),
-                BuildingHandle::new(
-                    "building-type-b",
-                    asset_server.load("models/buildings/building-type-b.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::C),
-                BuildingHandle::new(
-                    "building-type-c",
-                    asset_server.load("models/buildings/building-type-c.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::D),
-                BuildingHandle::new(
-                    "building-type-d",
-                    asset_server.load("models/buildings/building-type-d.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::E),
-                BuildingHandle::new(
-                    "building-type-e",
-                    asset_server.load("models/buildings/building-type-e.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::F),
-                BuildingHandle::new(
-                    "building-type-f",
-                    asset_server.load("models/buildings/building-type-f.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::G),
-                BuildingHandle::new(
-                    "building-type-g",
-                    asset_server.load("models/buildings/building-type-g.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::H),
-                BuildingHandle::new(
-                    "building-type-h",
-                    asset_server.load("models/buildings/building-type-h.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::I),
-                BuildingHandle::new(
-                    "building-type-i",
-                    asset_server.load("models/buildings/building-type-i.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::J),
-                BuildingHandle::new(
-                    "building-type-j",
-                    asset_server.load("models/buildings/building-type-j.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::K),
-                BuildingHandle::new(
-                    "building-type-k",
-                    asset_server.load("models/buildings/building-type-k.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::L),
-                BuildingHandle::new(
-                    "building-type-l",
-                    asset_server.load("models/buildings/building-type-l.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::M),
-                BuildingHandle::new(
-                    "building-type-m",
-                    asset_server.load("models/buildings/building-type-m.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::N),
-                BuildingHandle::new(
-                    "building-type-n",
-                    asset_server.load("models/buildings/building-type-n.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::O),
-                BuildingHandle::new(
-                    "building-type-o",
-                    asset_server.load("models/buildings/building-type-o.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::P),
-                BuildingHandle::new(
-                    "building-type-p",
-                    asset_server.load("models/buildings/building-type-p.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::Q),
-                BuildingHandle::new(
-                    "building-type-q",
-                    asset_server.load("models/buildings/building-type-q.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::R),
-                BuildingHandle::new(
-                    "building-type-r",
-                    asset_server.load("models/buildings/building-type-r.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::S),
-                BuildingHandle::new(
-                    "building-type-s",
-                    asset_server.load("models/buildings/building-type-s.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::T),
-                BuildingHandle::new(
-                    "building-type-t",
-                    asset_server.load("models/buildings/building-type-t.glb"),
-                ),
-            ),
-            (
-                BuildingType::Residential(ResidentialType::U),
-                BuildingHandle::new(
-                    "building-type-u",
-                    asset_server.load("models/buildings/building-type-u.glb"),
-                ),
-            ),
-            (
-                BuildingType::Driveway(DrivewayType::Long),
-                BuildingHandle::new(
-                    "driveway-long",
-                    asset_server.load("models/buildings/driveway-long.glb"),
-                ),
-            ),
-            (
-                BuildingType::Driveway(DrivewayType::Short),
-                BuildingHandle::new(
-                    "driveway-short",
-                    asset_server.load("models/buildings/driveway-short.glb"),
-                ),
-            ),
-            (
-                BuildingType::Fence(FenceType::OneByTwo),
-                BuildingHandle::new(
-                    "fence-1x2",
-                    asset_server.load("models/buildings/fence-1x2.glb"),
-                ),
-            ),
-            (
-                BuildingType::Fence(FenceType::OneByThree),
-                BuildingHandle::new(
-                    "fence-1x3",
-                    asset_server.load("models/buildings/fence-1x3.glb"),
-                ),
-            ),
-            (
-                BuildingType::Fence(FenceType::OneByFour),
-                BuildingHandle::new(
-                    "fence-1x4",
-                    asset_server.load("models/buildings/fence-1x4.glb"),
-                ),
-            ),
-            (
-                BuildingType::Fence(FenceType::TwoByTwo),
-                BuildingHandle::new(
-                    "fence-2x2",
-                    asset_server.load("models/buildings/fence-2x2.glb"),
-                ),
-            ),
-            (
-                BuildingType::Fence(FenceType::TwoByThree),
-                BuildingHandle::new(
-                    "fence-2x3",
-                    asset_server.load("models/buildings/fence-2x3.glb"),
-                ),
-            ),
-            (
-                BuildingType::Fence(FenceType::ThreeByTwo),
-                BuildingHandle::new(
-                    "fence-3x2",
-                    asset_server.load("models/buildings/fence-3x2.glb"),
-                ),
-            ),
-            (
-                BuildingType::Fence(FenceType::ThreeByThree),
-                BuildingHandle::new(
-                    "fence-3x3",
-                    asset_server.load("models/buildings/fence-3x3.glb"),
-                ),
-            ),
-            (
-                BuildingType::Fence(FenceType::Low),
-                BuildingHandle::new(
-                    "fence-low",
-                    asset_server.load("models/buildings/fence-low.glb"),
-                ),
-            ),
-            (
-                BuildingType::Fence(FenceType::Regular),
-                BuildingHandle::new("fence", asset_server.load("models/buildings/fence.glb")),
-            ),
-            (
-                BuildingType::Path(PathType::Long),
-                BuildingHandle::new(
-                    "path-long",
-                    asset_server.load("models/buildings/path-long.glb"),
-                ),
-            ),
-            (
-                BuildingType::Path(PathType::Short),
-                BuildingHandle::new(
-                    "path-short",
-                    asset_server.load("models/buildings/path-short.glb"),
-                ),
-            ),
-            (
-                BuildingType::Path(PathType::StonesLong),
-                BuildingHandle::new(
-                    "path-stones-long",
-                    asset_server.load("models/buildings/path-stones-long.glb"),
-                ),
-            ),
-            (
-                BuildingType::Path(PathType::StonesMessy),
-                BuildingHandle::new(
-                    "path-stones-messy",
-                    asset_server.load("models/buildings/path-stones-messy.glb"),
-                ),
-            ),
-            (
-                BuildingType::Path(PathType::StonesShort),
-                BuildingHandle::new(
-                    "path-stones-short",
-                    asset_server.load("models/buildings/path-stones-short.glb"),
-                ),
-            ),
-            (
-                BuildingType::Planter,
-                BuildingHandle::new("planter", asset_server.load("models/buildings/planter.glb")),
-            ),
-            (
-                BuildingType::Tree(TreeType::Large),
-                BuildingHandle::new(
-                    "tree-large",
-                    asset_server.load("models/buildings/tree-large.glb"),
-                ),
-            ),
-            (
-                BuildingType::Tree(TreeType::Small),
-                BuildingHandle::new(
-                    "tree-small",
-                    asset_server.load("models/buildings/tree-small.glb"),
-                ),
-            ),
-        ];
-        Self { buildings }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_catalog_is_valid() {
+        let catalog: BuildingCatalogFile =
+            ron::de::from_bytes(include_bytes!("../../../assets/buildings.catalog.ron")).unwrap();
+        catalog.validate().unwrap();
+        for building in &catalog.buildings {
+            let model = format!("{}/assets/{}", env!("CARGO_MANIFEST_DIR"), building.model);
+            assert!(
+                std::path::Path::new(&model).is_file(),
+                "{:?} has no model at {model}",
+                building.id
+            );
+        }
+    }
+
+    #[test]
+    fn footprints_turn_with_the_building() {
+        let orientation = BuildingOrientation::default();
+        assert_eq!(orientation.footprint(UVec2::new(3, 2)), UVec2::new(3, 2));
+        let turned = orientation.turned(-1);
+        assert_eq!(turned.quarter_turns, 3);
+        assert_eq!(turned.footprint(UVec2::new(3, 2)), UVec2::new(2, 3));
+        assert_eq!(turned.turned(3), orientation.turned(2));
+        assert_eq!(
+            turned.flipped().footprint(UVec2::new(3, 2)),
+            UVec2::new(2, 3)
+        );
+    }
+
+    #[test]
+    fn mirroring_happens_before_turning() {
+        let orientation = BuildingOrientation {
+            quarter_turns: 1,
+            mirrored: true,
+        };
+        let point = orientation.apply(Vec3::new(1.0, 0.5, 0.0));
+        assert!(point.abs_diff_eq(Vec3::new(0.0, 0.5, 1.0), 1e-6), "{point}");
+    }
+
+    #[test]
+    fn ids_must_be_unique() {
+        let catalog: BuildingCatalogFile = ron::de::from_str(
+            r#"(buildings: [
+                (id: "tree", name: "Tree", category: Tree, model: "tree.glb"),
+                (id: "planter", name: "Planter", category: Planter, model: "planter.glb"),
+                (id: "tree", name: "Big Tree", category: Tree, model: "tree-large.glb"),
+            ])"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            catalog.validate(),
+            Err(BuildingCatalogError::DuplicateId(BuildingId(id))) if id == "tree"
+        ));
     }
 }
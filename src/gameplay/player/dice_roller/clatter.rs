@@ -0,0 +1,187 @@
+//! The clatter of dice hitting the tray and each other.
+//!
+//! Every time a die starts touching something, an impact sound is played. It gets louder
+//! and higher-pitched the harder the impact, and each die only clatters so often, so that
+//! a die rolling across the tray doesn't turn into noise.
+
+use std::time::Duration;
+
+use avian3d::prelude::*;
+use bevy::{audio::Volume, prelude::*};
+use rand::seq::SliceRandom;
+
+use super::Die;
+use crate::{asset_tracking::LoadResource, audio::sound_effect};
+
+pub(super) fn plugin(app: &mut App) {
+    app.load_resource::<DiceClatterAssets>();
+
+    app.add_systems(
+        FixedPostUpdate,
+        (play_clatter, remember_velocities)
+            .chain()
+            .after(PhysicsSet::StepSimulation),
+    );
+}
+
+/// Impacts slower than this, relative to what the die hit, make no sound.
+const MIN_IMPACT_SPEED: f32 = 0.5;
+/// Impacts at least this fast, relative to what the die hit, play at full volume.
+const LOUD_IMPACT_SPEED: f32 = 8.0;
+/// Playback speed of the softest and the loudest impacts, which raises their pitch.
+const PITCH_RANGE: (f32, f32) = (0.85, 1.2);
+/// The shortest time between two impact sounds of the same die.
+const CLATTER_COOLDOWN: Duration = Duration::from_millis(80);
+
+#[derive(Resource, Asset, Clone, Reflect)]
+#[reflect(Resource)]
+struct DiceClatterAssets {
+    #[dependency]
+    impacts: Vec<Handle<AudioSource>>,
+}
+
+impl FromWorld for DiceClatterAssets {
+    fn from_world(world: &mut World) -> Self {
+        let assets = world.resource::<AssetServer>();
+        // There are no recordings of dice yet, so short clicks stand in for the impacts.
+        Self {
+            impacts: vec![
+                assets.load("audio/sound_effects/click1.ogg"),
+                assets.load("audio/sound_effects/button_click.ogg"),
+            ],
+        }
+    }
+}
+
+/// How an impact sounds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ImpactSound {
+    /// Linear volume, from 0 to 1.
+    volume: f32,
+    /// Playback speed, which also scales the pitch.
+    speed: f32,
+}
+
+impl ImpactSound {
+    /// The sound of an impact at the given speed, relative to what the die hit.
+    /// Returns `None` if the impact is too soft to be heard.
+    fn from_impact_speed(speed: f32) -> Option<Self> {
+        if speed < MIN_IMPACT_SPEED {
+            return None;
+        }
+        let strength =
+            ((speed - MIN_IMPACT_SPEED) / (LOUD_IMPACT_SPEED - MIN_IMPACT_SPEED)).clamp(0.0, 1.0);
+        Some(Self {
+            volume: strength.sqrt(),
+            speed: PITCH_RANGE.0.lerp(PITCH_RANGE.1, strength),
+        })
+    }
+}
+
+/// How a die moved before the latest physics step, and when it last clattered.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub(super) struct Clatter {
+    /// The velocity before the latest physics step, as the impact has already slowed
+    /// the die down once the collision is reported.
+    velocity: Vec3,
+    last_played: Option<Duration>,
+}
+
+impl Clatter {
+    /// Whether the die may clatter again at the given time.
+    fn is_ready(&self, now: Duration) -> bool {
+        self.last_played
+            .is_none_or(|last_played| now.saturating_sub(last_played) >= CLATTER_COOLDOWN)
+    }
+}
+
+/// Plays an impact sound whenever a die starts touching the tray or another die
+fn play_clatter(
+    mut commands: Commands,
+    time: Res<Time>,
+    assets: Option<Res<DiceClatterAssets>>,
+    mut collisions: EventReader<CollisionStarted>,
+    mut dice: Query<&mut Clatter, With<Die>>,
+) {
+    let Some(assets) = assets else {
+        collisions.clear();
+        return;
+    };
+    let now = time.elapsed();
+    let rng = &mut rand::thread_rng();
+
+    for &CollisionStarted(a, b) in collisions.read() {
+        let velocity = |entity| dice.get(entity).map(|clatter| clatter.velocity).ok();
+        let (velocity_a, velocity_b) = (velocity(a), velocity(b));
+        if velocity_a.is_none() && velocity_b.is_none() {
+            continue;
+        }
+        // The tray doesn't move, so anything that isn't a die counts as standing still.
+        let impact_speed =
+            (velocity_a.unwrap_or(Vec3::ZERO) - velocity_b.unwrap_or(Vec3::ZERO)).length();
+        let Some(sound) = ImpactSound::from_impact_speed(impact_speed) else {
+            continue;
+        };
+
+        // Dice hitting each other make a single sound, if either of them may clatter.
+        let [clatter_a, clatter_b] = [a, b].map(|entity| dice.get(entity).ok().copied());
+        if ![clatter_a, clatter_b]
+            .into_iter()
+            .flatten()
+            .any(|clatter| clatter.is_ready(now))
+        {
+            continue;
+        }
+        for entity in [a, b] {
+            if let Ok(mut clatter) = dice.get_mut(entity) {
+                clatter.last_played = Some(now);
+            }
+        }
+
+        let Some(impact) = assets.impacts.choose(rng) else {
+            continue;
+        };
+        commands.spawn(sound_effect(impact.clone())).insert(
+            PlaybackSettings::DESPAWN
+                .with_volume(Volume::Linear(sound.volume))
+                .with_speed(sound.speed),
+        );
+    }
+}
+
+/// Remembers how fast each die moves, to measure the impacts of the next physics step
+fn remember_velocities(mut dice: Query<(&mut Clatter, &LinearVelocity)>) {
+    for (mut clatter, velocity) in &mut dice {
+        clatter.velocity = velocity.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn harder_impacts_sound_louder_and_higher() {
+        assert_eq!(ImpactSound::from_impact_speed(0.1), None);
+
+        let soft = ImpactSound::from_impact_speed(1.0).unwrap();
+        let hard = ImpactSound::from_impact_speed(6.0).unwrap();
+        assert!(soft.volume < hard.volume);
+        assert!(soft.speed < hard.speed);
+
+        let loudest = ImpactSound::from_impact_speed(100.0).unwrap();
+        assert_eq!(loudest.volume, 1.0);
+        assert!((loudest.speed - PITCH_RANGE.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn clatter_is_rate_limited() {
+        let clatter = Clatter {
+            last_played: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        assert!(!clatter.is_ready(Duration::from_secs(1) + CLATTER_COOLDOWN / 2));
+        assert!(clatter.is_ready(Duration::from_secs(1) + CLATTER_COOLDOWN));
+        assert!(Clatter::default().is_ready(Duration::ZERO));
+    }
+}
@@ -0,0 +1,72 @@
+//! Table rules for dice that come to rest on an edge or corner instead of flat on a face.
+
+use bevy::prelude::*;
+
+/// What to do with a die that came to rest cocked.
+#[derive(Reflect, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CockedPolicy {
+    /// Give the die a small push until it falls flat.
+    #[default]
+    Nudge,
+    /// Throw the die again.
+    Reroll,
+    /// Turn the die flat onto the face closest to being read.
+    Snap,
+}
+
+/// How cocked dice are resolved.
+#[derive(Resource, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Resource)]
+pub struct CockedRules {
+    pub policy: CockedPolicy,
+    /// How many times a die may be nudged or rerolled before it is snapped flat instead.
+    pub max_attempts: u32,
+}
+
+impl Default for CockedRules {
+    fn default() -> Self {
+        Self {
+            policy: CockedPolicy::Nudge,
+            max_attempts: 5,
+        }
+    }
+}
+
+impl CockedRules {
+    /// The policy to apply to a die that came to rest cocked for the `attempt`th time,
+    /// counting from 1.
+    pub fn policy_for(&self, attempt: u32) -> CockedPolicy {
+        if attempt > self.max_attempts {
+            CockedPolicy::Snap
+        } else {
+            self.policy
+        }
+    }
+}
+
+/// How a die that came to rest cocked was resolved, reported with its roll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CockedResolution {
+    /// The policy applied the last time the die was cocked.
+    pub policy: CockedPolicy,
+    /// How many times the die came to rest cocked.
+    pub attempts: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_snapping_after_the_retry_cap() {
+        for policy in [CockedPolicy::Nudge, CockedPolicy::Reroll] {
+            let rules = CockedRules {
+                policy,
+                max_attempts: 2,
+            };
+            assert_eq!(rules.policy_for(1), policy);
+            assert_eq!(rules.policy_for(2), policy);
+            assert_eq!(rules.policy_for(3), CockedPolicy::Snap);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Picking up a die with the pointer and flicking it into the tray.
+//!
+//! Dragging a die lifts it above the tray and carries it under the pointer. Releasing it
+//! rolls the pool, throwing the carried die with the velocity the pointer moved at just
+//! before the release.
+
+use std::{collections::VecDeque, time::Duration};
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use super::{
+    Die, RollRequested,
+    hold::{Held, Turn, TurnRules},
+    physics_config::DicePhysicsConfig,
+    pool::DicePool,
+    roll_blocked,
+    tray::DiceTray,
+};
+use crate::menus::Menu;
+
+/// Height above the tray a die is carried at.
+const CARRY_HEIGHT: f32 = 2.5;
+/// How close to the walls of the tray a carried die may get.
+const CARRY_MARGIN: f32 = 0.4;
+/// How far back the pointer motion is followed to measure the flick.
+const FLICK_WINDOW: Duration = Duration::from_millis(100);
+/// The fastest a die can be flicked, so that it stays inside the tray.
+const MAX_FLICK_SPEED: f32 = 12.0;
+
+/// A die thrown by hand rather than with a drawn impulse.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct Flick {
+    pub(super) entity: Entity,
+    /// Linear impulse to throw the die with, replacing the drawn one.
+    pub(super) impulse: Vec3,
+}
+
+/// The recent positions of a carried die, to measure how fast it was moving when released.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct PointerTrail {
+    samples: VecDeque<(Duration, Vec3)>,
+}
+
+impl PointerTrail {
+    /// Adds the position at the given time, forgetting samples older than [`FLICK_WINDOW`].
+    fn push(&mut self, now: Duration, position: Vec3) {
+        self.samples.push_back((now, position));
+        while self
+            .samples
+            .front()
+            .is_some_and(|&(time, _)| now.saturating_sub(time) > FLICK_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// The average velocity over the trail, limited to [`MAX_FLICK_SPEED`].
+    fn velocity(&self) -> Vec3 {
+        let (Some(&(start, from)), Some(&(end, to))) = (self.samples.front(), self.samples.back())
+        else {
+            return Vec3::ZERO;
+        };
+        let elapsed = end.saturating_sub(start).as_secs_f32();
+        if elapsed <= 0.0 {
+            return Vec3::ZERO;
+        }
+        ((to - from) / elapsed).clamp_length_max(MAX_FLICK_SPEED)
+    }
+}
+
+/// A die being carried by the pointer.
+#[derive(Component, Debug)]
+pub(super) struct Carried {
+    /// The camera the die was picked up through.
+    camera: Entity,
+    trail: PointerTrail,
+}
+
+/// Picks up a die when it starts being dragged, if the pool may be rolled
+pub(super) fn pick_up(
+    mut trigger: Trigger<Pointer<DragStart>>,
+    mut commands: Commands,
+    time: Res<Time>,
+    menu: Res<State<Menu>>,
+    pool: Res<DicePool>,
+    turn_rules: Res<TurnRules>,
+    turn: Res<Turn>,
+    held: Query<Entity, With<Held>>,
+    mut dice: Query<(&Transform, &mut RigidBody), With<Die>>,
+) {
+    if trigger.event().button != PointerButton::Primary {
+        return;
+    }
+    trigger.propagate(false);
+    // Rolling waits until every menu is closed, which also keeps the game unpaused.
+    if *menu.get() != Menu::None {
+        return;
+    }
+    let entity = trigger.target();
+    let held: Vec<Entity> = held.iter().collect();
+    if held.contains(&entity) {
+        return;
+    }
+    if let Some(reason) = roll_blocked(&pool, &turn, &turn_rules, &held) {
+        info!("{reason}");
+        return;
+    }
+    let Ok((transform, mut rigid_body)) = dice.get_mut(entity) else {
+        return;
+    };
+
+    *rigid_body = RigidBody::Kinematic;
+    let mut trail = PointerTrail::default();
+    trail.push(time.elapsed(), transform.translation);
+    commands.entity(entity).insert(Carried {
+        camera: trigger.event().hit.camera,
+        trail,
+    });
+}
+
+/// Moves a carried die under the pointer, above the tray
+pub(super) fn carry(
+    mut trigger: Trigger<Pointer<Drag>>,
+    time: Res<Time>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    trays: Query<(&DiceTray, &GlobalTransform)>,
+    mut dice: Query<(&mut Transform, &mut Carried)>,
+) {
+    let Ok((mut transform, mut carried)) = dice.get_mut(trigger.target()) else {
+        return;
+    };
+    trigger.propagate(false);
+    let Ok((camera, camera_transform)) = cameras.get(carried.camera) else {
+        return;
+    };
+    let Ok(ray) =
+        camera.viewport_to_world(camera_transform, trigger.event().pointer_location.position)
+    else {
+        return;
+    };
+    let Some(distance) = ray.intersect_plane(Vec3::Y * CARRY_HEIGHT, InfinitePlane3d::new(Vec3::Y))
+    else {
+        return;
+    };
+
+    let mut position = ray.get_point(distance);
+    if let Some((tray, tray_transform)) = trays.iter().next() {
+        let center = tray_transform.translation();
+        position = center + tray.clamp_inside(position - center, CARRY_MARGIN);
+    }
+    transform.translation = position;
+    carried.trail.push(time.elapsed(), position);
+}
+
+/// Drops a carried die when the drag ends, and rolls the pool with it
+pub(super) fn release(
+    mut trigger: Trigger<Pointer<DragEnd>>,
+    mut commands: Commands,
+    config: Res<DicePhysicsConfig>,
+    mut requests: EventWriter<RollRequested>,
+    mut dice: Query<(
+        &Carried,
+        &mut RigidBody,
+        &mut LinearVelocity,
+        &mut AngularVelocity,
+    )>,
+) {
+    let entity = trigger.target();
+    let Ok((carried, mut rigid_body, mut linear_velocity, mut angular_velocity)) =
+        dice.get_mut(entity)
+    else {
+        return;
+    };
+    trigger.propagate(false);
+
+    *rigid_body = RigidBody::Dynamic;
+    linear_velocity.0 = Vec3::ZERO;
+    angular_velocity.0 = Vec3::ZERO;
+    requests.write(RollRequested {
+        flick: Some(Flick {
+            entity,
+            impulse: carried.trail.velocity() * config.mass,
+        }),
+    });
+    commands.entity(entity).remove::<Carried>();
+}
+
+/// Drops every carried die as soon as a roll is requested, so that it is thrown with the
+/// rest of the pool, or, if the roll is blocked, isn't left on the pointer with a stale trail
+pub(super) fn drop_carried_dice(
+    mut commands: Commands,
+    mut requests: EventReader<RollRequested>,
+    mut dice: Query<(Entity, &mut RigidBody), With<Carried>>,
+) {
+    if requests.read().count() == 0 {
+        return;
+    }
+    for (entity, mut rigid_body) in &mut dice {
+        *rigid_body = RigidBody::Dynamic;
+        commands.entity(entity).remove::<Carried>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flick_velocity_follows_recent_motion() {
+        let mut trail = PointerTrail::default();
+        // A slow drag, followed by a quick flick along X.
+        for step in 0..10 {
+            trail.push(
+                Duration::from_millis(step * 50),
+                Vec3::Z * step as f32 * 0.01,
+            );
+        }
+        let start = trail.samples.back().unwrap().1;
+        for step in 1..=5 {
+            let now = Duration::from_millis(450 + step * 20);
+            trail.push(now, start + Vec3::X * step as f32 * 0.1);
+        }
+
+        let velocity = trail.velocity();
+        assert!((velocity.x - 5.0).abs() < 1e-4, "{velocity}");
+        assert!(velocity.z.abs() < 1e-4, "{velocity}");
+    }
+
+    #[test]
+    fn flick_velocity_is_limited() {
+        let mut trail = PointerTrail::default();
+        trail.push(Duration::ZERO, Vec3::ZERO);
+        trail.push(Duration::from_millis(10), Vec3::X * 10.0);
+        assert_eq!(trail.velocity(), Vec3::X * MAX_FLICK_SPEED);
+
+        assert_eq!(PointerTrail::default().velocity(), Vec3::ZERO);
+    }
+}
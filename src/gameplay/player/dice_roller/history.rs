@@ -0,0 +1,347 @@
+//! A log of every completed roll, with an in-game panel showing how fair the dice have been.
+
+use std::time::Duration;
+
+use bevy::{
+    input::common_conditions::input_just_pressed, platform::collections::HashMap, prelude::*,
+};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+
+use super::{
+    kind::DieKind,
+    notation::RollBreakdown,
+    pool::{DicePool, DicePoolRolled},
+    roll_params::DiceRng,
+};
+use crate::screens::Screen;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<RollHistory>();
+    app.init_resource::<RollHistoryPanel>();
+
+    app.add_systems(Update, record_rolls);
+    app.add_systems(
+        Update,
+        toggle_history_panel.run_if(in_state(Screen::Gameplay).and(input_just_pressed(TOGGLE_KEY))),
+    );
+    app.add_systems(
+        EguiPrimaryContextPass,
+        draw_history_panel
+            .run_if(in_state(Screen::Gameplay).and(resource_equals(RollHistoryPanel(true)))),
+    );
+}
+
+const TOGGLE_KEY: KeyCode = KeyCode::Tab;
+/// How many of the latest rolls the panel lists.
+const RECENT_ROLLS: usize = 10;
+
+/// A completed roll of the dice pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RollRecord {
+    /// The dice expression that was rolled, e.g. `2d6+1`.
+    pub expression: String,
+    /// The face of every die counted, including exploded, dropped and held ones.
+    pub breakdown: RollBreakdown,
+    /// The face of every die thrown, for the fairness stats. Held dice weren't thrown by
+    /// this roll and are left out, so that a face is only counted once. So are faces a
+    /// loaded die overrode, which physics didn't decide.
+    pub faces: Vec<(DieKind, u32)>,
+    pub total: i64,
+    /// The seed the roll was thrown with, to replay it.
+    pub seed: u64,
+    /// How long the dice took to settle.
+    pub settle_time: Duration,
+    /// How many times a die came to rest cocked and had to be resolved.
+    pub cocked_retries: u32,
+    /// Whether a loaded die overrode any face of the roll, rather than physics deciding it.
+    pub overridden: bool,
+}
+
+impl RollRecord {
+    /// The record of a roll of the given expression. Only the dice thrown by the roll count
+    /// towards its stats, so that held dice aren't counted again on every roll.
+    fn new(expression: String, rolled: &DicePoolRolled) -> Self {
+        Self {
+            expression,
+            breakdown: rolled.breakdown.clone(),
+            faces: rolled
+                .thrown
+                .iter()
+                .filter(|(_, die)| !die.overridden)
+                .map(|(kind, die)| (*kind, die.face))
+                .collect(),
+            total: rolled.total,
+            seed: rolled.seed,
+            settle_time: rolled.settle_time,
+            cocked_retries: rolled
+                .thrown
+                .iter()
+                .filter_map(|(_, die)| die.cocked)
+                .map(|cocked| cocked.attempts)
+                .sum(),
+            overridden: rolled.thrown.iter().any(|(_, die)| die.overridden),
+        }
+    }
+}
+
+/// Every roll completed this session, and how often each face of each kind of die came up.
+#[derive(Resource, Debug, Default)]
+pub struct RollHistory {
+    rolls: Vec<RollRecord>,
+    face_counts: HashMap<DieKind, Vec<u64>>,
+}
+
+impl RollHistory {
+    /// Records a completed roll.
+    pub fn record(&mut self, record: RollRecord) {
+        for &(kind, face) in &record.faces {
+            let counts = self
+                .face_counts
+                .entry(kind)
+                .or_insert_with(|| vec![0; kind.sides() as usize]);
+            if let Some(count) = counts.get_mut(face as usize - 1) {
+                *count += 1;
+            }
+        }
+        self.rolls.push(record);
+    }
+
+    /// Every roll, oldest first.
+    pub fn rolls(&self) -> &[RollRecord] {
+        &self.rolls
+    }
+
+    /// How often each face of the given kind of die came up, indexed by face value minus one.
+    pub fn face_counts(&self, kind: DieKind) -> Option<&[u64]> {
+        self.face_counts.get(&kind).map(Vec::as_slice)
+    }
+}
+
+/// Pearson's chi-square test of how well face counts match a fair die.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChiSquare {
+    pub statistic: f64,
+    pub degrees_of_freedom: u32,
+    /// The probability of a fair die deviating at least this much. Low values hint at bias.
+    pub p_value: f64,
+}
+
+impl ChiSquare {
+    /// Tests the given face counts against a uniform distribution.
+    /// Returns `None` if there are fewer than two faces or no rolls at all.
+    pub fn test(counts: &[u64]) -> Option<Self> {
+        let total: u64 = counts.iter().sum();
+        if counts.len() < 2 || total == 0 {
+            return None;
+        }
+
+        let expected = total as f64 / counts.len() as f64;
+        let statistic = counts
+            .iter()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum();
+        let degrees_of_freedom = counts.len() as u32 - 1;
+        Some(Self {
+            statistic,
+            degrees_of_freedom,
+            p_value: chi_square_survival(statistic, degrees_of_freedom),
+        })
+    }
+}
+
+/// The probability of a chi-square distributed value exceeding `x`, using the
+/// Wilson-Hilferty approximation, which is plenty for a fairness readout.
+fn chi_square_survival(x: f64, degrees_of_freedom: u32) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let k = degrees_of_freedom as f64;
+    let variance = 2.0 / (9.0 * k);
+    let z = ((x / k).cbrt() - (1.0 - variance)) / variance.sqrt();
+    0.5 * erfc(z / std::f64::consts::SQRT_2)
+}
+
+/// The complementary error function, from Abramowitz and Stegun 7.1.26.
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let polynomial = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let erfc = polynomial * (-x * x).exp();
+    if x >= 0.0 { erfc } else { 2.0 - erfc }
+}
+
+/// Whether the roll history panel is shown.
+#[derive(Resource, Reflect, Default, PartialEq)]
+#[reflect(Resource)]
+struct RollHistoryPanel(bool);
+
+fn toggle_history_panel(mut panel: ResMut<RollHistoryPanel>) {
+    panel.0 = !panel.0;
+}
+
+fn record_rolls(
+    pool: Res<DicePool>,
+    mut pool_rolled: EventReader<DicePoolRolled>,
+    mut history: ResMut<RollHistory>,
+) {
+    for rolled in pool_rolled.read() {
+        history.record(RollRecord::new(pool.expression().to_string(), rolled));
+    }
+}
+
+fn draw_history_panel(
+    mut ctxs: EguiContexts,
+    history: Res<RollHistory>,
+    rng: Res<DiceRng>,
+) -> Result {
+    let ctx = ctxs.ctx_mut()?;
+    egui::Window::new("Roll History")
+        .default_width(280.0)
+        .show(ctx, |ui| {
+            ui.label(format!("Session seed: {}", rng.seed()));
+            ui.label(format!("Rolls: {}", history.rolls().len()));
+
+            ui.heading("Recent rolls");
+            for roll in history.rolls().iter().rev().take(RECENT_ROLLS) {
+                ui.label(format!(
+                    "{}: {} ({:.2} s, seed {}{}{})",
+                    roll.expression,
+                    roll.breakdown,
+                    roll.settle_time.as_secs_f32(),
+                    roll.seed,
+                    match roll.cocked_retries {
+                        0 => String::new(),
+                        retries => format!(", {retries} cocked"),
+                    },
+                    if roll.overridden { ", loaded" } else { "" },
+                ));
+            }
+
+            for kind in DieKind::ALL {
+                let Some(counts) = history.face_counts(kind) else {
+                    continue;
+                };
+                let total: u64 = counts.iter().sum();
+                let most = counts.iter().copied().max().unwrap_or(0).max(1);
+                ui.separator();
+                ui.heading(format!("d{} faces ({total} rolled)", kind.sides()));
+                for (face, &count) in counts.iter().enumerate() {
+                    ui.add(
+                        egui::ProgressBar::new(count as f32 / most as f32)
+                            .text(format!("{}: {count}", face + 1)),
+                    );
+                }
+                if let Some(chi_square) = ChiSquare::test(counts) {
+                    ui.label(format!(
+                        "χ² = {:.2} with {} degrees of freedom, p = {:.3}",
+                        chi_square.statistic, chi_square.degrees_of_freedom, chi_square.p_value
+                    ));
+                }
+            }
+        });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameplay::player::dice_roller::{
+        DieRolled,
+        cocked::{CockedPolicy, CockedResolution},
+        notation::{DieOutcome, TermBreakdown},
+    };
+
+    fn breakdown(faces: &[u32]) -> RollBreakdown {
+        RollBreakdown {
+            terms: vec![TermBreakdown::Dice {
+                negative: false,
+                kind: DieKind::D6,
+                dice: faces
+                    .iter()
+                    .map(|&face| DieOutcome {
+                        face,
+                        kept: true,
+                        exploded: false,
+                        from_explosion: false,
+                    })
+                    .collect(),
+                subtotal: faces.iter().sum::<u32>() as i64,
+            }],
+            total: faces.iter().sum::<u32>() as i64,
+        }
+    }
+
+    fn record(faces: &[u32]) -> RollRecord {
+        RollRecord {
+            expression: format!("{}d6", faces.len()),
+            breakdown: breakdown(faces),
+            faces: faces.iter().map(|&face| (DieKind::D6, face)).collect(),
+            total: faces.iter().sum::<u32>() as i64,
+            seed: 0,
+            settle_time: Duration::ZERO,
+            cocked_retries: 0,
+            overridden: false,
+        }
+    }
+
+    #[test]
+    fn counts_faces_per_kind() {
+        let mut history = RollHistory::default();
+        history.record(record(&[6, 2]));
+        history.record(record(&[6, 6, 1]));
+        assert_eq!(history.rolls().len(), 2);
+        assert_eq!(
+            history.face_counts(DieKind::D6),
+            Some(&[1, 1, 0, 0, 0, 3][..])
+        );
+        assert_eq!(history.face_counts(DieKind::D20), None);
+    }
+
+    #[test]
+    fn held_dice_dont_count_again() {
+        let cocked = |entity, face, attempts| DieRolled {
+            entity: Entity::from_raw(entity),
+            face,
+            cocked: Some(CockedResolution {
+                policy: CockedPolicy::Nudge,
+                attempts,
+            }),
+            overridden: false,
+        };
+        // The first die was held after coming to rest cocked twice on an earlier roll.
+        let held = cocked(1, 4, 2);
+        let thrown = cocked(2, 5, 1);
+        let rolled = DicePoolRolled {
+            dice: vec![held, thrown],
+            breakdown: breakdown(&[4, 5]),
+            thrown: vec![(DieKind::D6, thrown)],
+            total: 9,
+            seed: 0,
+            settle_time: Duration::ZERO,
+        };
+        let record = RollRecord::new("2d6".to_string(), &rolled);
+        assert_eq!(record.cocked_retries, 1);
+        assert_eq!(record.faces, [(DieKind::D6, 5)]);
+    }
+
+    #[test]
+    fn chi_square_of_a_fair_sample_is_zero() {
+        let chi_square = ChiSquare::test(&[10; 6]).unwrap();
+        assert_eq!(chi_square.statistic, 0.0);
+        assert_eq!(chi_square.degrees_of_freedom, 5);
+        assert_eq!(chi_square.p_value, 1.0);
+        assert_eq!(ChiSquare::test(&[0; 6]), None);
+    }
+
+    #[test]
+    fn chi_square_p_value_matches_critical_values() {
+        // The 5% and 1% critical values of the chi-square distribution.
+        for (x, degrees_of_freedom, p) in [(11.07, 5, 0.05), (15.086, 5, 0.01), (30.144, 19, 0.05)]
+        {
+            let approximation = chi_square_survival(x, degrees_of_freedom);
+            assert!((approximation - p).abs() < 0.002, "{x}: {approximation}");
+        }
+    }
+}
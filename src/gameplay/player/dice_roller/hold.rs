@@ -0,0 +1,261 @@
+//! Holding dice between rolls, and the turn rules that limit how often a player may reroll.
+//!
+//! Right-click a settled die to hold it, so that it keeps its face on the next roll, and
+//! press [`END_TURN_KEY`] to release every die and start a new turn.
+
+use avian3d::prelude::*;
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+
+use super::{Die, DieState, flick::Carried, pool::DicePool, replay::DiceThrown, roll_dice};
+use crate::{PausableSystems, menus::Menu, screens::Screen};
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<Held>();
+    app.register_type::<TurnRules>();
+    app.init_resource::<TurnRules>();
+    app.init_resource::<Turn>();
+    app.init_resource::<HeldHighlight>();
+
+    app.add_observer(highlight_held_die);
+    app.add_observer(remove_held_highlight);
+
+    app.add_systems(
+        Update,
+        end_turn.in_set(PausableSystems).run_if(
+            in_state(Screen::Gameplay)
+                .and(in_state(Menu::None))
+                .and(input_just_pressed(END_TURN_KEY)),
+        ),
+    );
+    app.add_systems(
+        Update,
+        (pin_held_dice.after(roll_dice), release_held_dice).in_set(PausableSystems),
+    );
+    app.add_systems(OnExit(Screen::Gameplay), reset_turn);
+}
+
+const END_TURN_KEY: KeyCode = KeyCode::Enter;
+
+/// A die that keeps its face instead of being thrown with the next roll.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[reflect(Component)]
+pub struct Held;
+
+/// How many times the dice may be rolled in a single turn.
+#[derive(Resource, Reflect, Debug, Clone, PartialEq, Eq)]
+#[reflect(Resource)]
+pub struct TurnRules {
+    /// How many times the dice may be rolled again after the first roll of a turn,
+    /// or `None` for no limit.
+    pub max_rerolls: Option<u32>,
+}
+
+impl Default for TurnRules {
+    fn default() -> Self {
+        Self {
+            max_rerolls: Some(2),
+        }
+    }
+}
+
+/// The progress of the current turn.
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Turn {
+    rolls: u32,
+}
+
+impl Turn {
+    /// Whether the rules allow rolling again this turn.
+    pub fn can_roll(&self, rules: &TurnRules) -> bool {
+        rules
+            .max_rerolls
+            .is_none_or(|max_rerolls| self.rolls <= max_rerolls)
+    }
+
+    /// Counts a roll towards the turn.
+    pub fn record_roll(&mut self) {
+        self.rolls += 1;
+    }
+
+    /// How many rolls are left this turn, if they are limited.
+    pub fn rolls_left(&self, rules: &TurnRules) -> Option<u32> {
+        let max_rerolls = rules.max_rerolls?;
+        Some((max_rerolls + 1).saturating_sub(self.rolls))
+    }
+}
+
+/// Toggles [`Held`] on a settled die when it is right-clicked
+pub(super) fn toggle_held(
+    mut trigger: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    menu: Res<State<Menu>>,
+    pool: Res<DicePool>,
+    dice: Query<(&Die, Has<Held>)>,
+) {
+    if trigger.event().button != PointerButton::Secondary {
+        return;
+    }
+    // The click is for the die, not for anything behind it, such as cancelling a building.
+    trigger.propagate(false);
+    if *menu.get() != Menu::None {
+        return;
+    }
+    let entity = trigger.target();
+    let Ok((die, held)) = dice.get(entity) else {
+        return;
+    };
+    // Only a die that has settled on a face this turn can keep it.
+    if pool.is_rolling() || die.state != DieState::Stationary || !pool.can_hold(entity) {
+        return;
+    }
+
+    if held {
+        commands.entity(entity).remove::<Held>();
+    } else {
+        commands.entity(entity).insert(Held);
+    }
+}
+
+/// Makes the dice left out of a throw kinematic, so that the thrown dice can't knock them
+/// off the face they are held on
+fn pin_held_dice(
+    mut thrown: EventReader<DiceThrown>,
+    pool: Res<DicePool>,
+    mut dice: Query<(&mut RigidBody, &mut LinearVelocity, &mut AngularVelocity), Without<Carried>>,
+) {
+    for throw in thrown.read() {
+        for entity in pool.entities() {
+            if throw.dice.iter().any(|&(thrown, _)| thrown == entity) {
+                continue;
+            }
+            let Ok((mut rigid_body, mut velocity, mut ang_velocity)) = dice.get_mut(entity) else {
+                continue;
+            };
+            *rigid_body = RigidBody::Kinematic;
+            velocity.0 = Vec3::ZERO;
+            ang_velocity.0 = Vec3::ZERO;
+        }
+    }
+}
+
+/// Lets the held dice move again once the roll is over
+fn release_held_dice(
+    pool: Res<DicePool>,
+    mut dice: Query<&mut RigidBody, (With<Die>, Without<Carried>)>,
+) {
+    if pool.is_rolling() {
+        return;
+    }
+    for mut rigid_body in &mut dice {
+        if *rigid_body == RigidBody::Kinematic {
+            *rigid_body = RigidBody::Dynamic;
+        }
+    }
+}
+
+/// The mesh and material that mark a held die.
+#[derive(Resource)]
+struct HeldHighlight {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for HeldHighlight {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world.resource_mut::<Assets<Mesh>>().add(Sphere::new(0.6));
+        let material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(StandardMaterial {
+                base_color: Color::srgba(1.0, 0.85, 0.2, 0.35),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..Default::default()
+            });
+        Self { mesh, material }
+    }
+}
+
+/// Marks the highlight of a held die.
+#[derive(Component)]
+struct HeldHighlightMarker;
+
+fn highlight_held_die(
+    trigger: Trigger<OnAdd, Held>,
+    mut commands: Commands,
+    highlight: Res<HeldHighlight>,
+) {
+    commands.entity(trigger.target()).with_child((
+        Name::new("Held Highlight"),
+        HeldHighlightMarker,
+        Mesh3d(highlight.mesh.clone()),
+        MeshMaterial3d(highlight.material.clone()),
+    ));
+}
+
+fn remove_held_highlight(
+    trigger: Trigger<OnRemove, Held>,
+    mut commands: Commands,
+    children: Query<&Children>,
+    highlights: Query<(), With<HeldHighlightMarker>>,
+) {
+    let Ok(children) = children.get(trigger.target()) else {
+        return;
+    };
+    for &child in children {
+        if highlights.contains(child) {
+            commands.entity(child).despawn();
+        }
+    }
+}
+
+/// Releases every held die and starts a new turn
+fn end_turn(
+    mut commands: Commands,
+    pool: Res<DicePool>,
+    mut turn: ResMut<Turn>,
+    held: Query<Entity, With<Held>>,
+) {
+    if pool.is_rolling() {
+        return;
+    }
+    for entity in &held {
+        commands.entity(entity).remove::<Held>();
+    }
+    *turn = Turn::default();
+    info!("New turn!");
+}
+
+fn reset_turn(mut turn: ResMut<Turn>) {
+    *turn = Turn::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_rerolls_per_turn() {
+        let rules = TurnRules {
+            max_rerolls: Some(2),
+        };
+        let mut turn = Turn::default();
+        for rolls_left in [3, 2, 1] {
+            assert!(turn.can_roll(&rules));
+            assert_eq!(turn.rolls_left(&rules), Some(rolls_left));
+            turn.record_roll();
+        }
+        assert!(!turn.can_roll(&rules));
+        assert_eq!(turn.rolls_left(&rules), Some(0));
+    }
+
+    #[test]
+    fn no_limit_without_max_rerolls() {
+        let rules = TurnRules { max_rerolls: None };
+        let mut turn = Turn::default();
+        for _ in 0..100 {
+            turn.record_roll();
+        }
+        assert!(turn.can_roll(&rules));
+        assert_eq!(turn.rolls_left(&rules), None);
+    }
+}
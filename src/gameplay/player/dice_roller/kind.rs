@@ -0,0 +1,549 @@
+//! The shapes of dice that can be rolled, with their collider and face tables.
+
+use std::{
+    f32::consts::{PI, TAU},
+    sync::LazyLock,
+};
+
+use avian3d::prelude::*;
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::mesh::{Indices, PrimitiveTopology},
+};
+use serde::{Deserialize, Serialize};
+
+/// Circumradius of the generated polyhedra, roughly matching the cube's collider.
+const RADIUS: f32 = 0.45;
+/// The golden ratio, used to build the dodecahedron and icosahedron.
+const PHI: f32 = 1.618_034;
+
+const CUBE_SIDES: [Vec3; 6] = [
+    Vec3::new(0.0, 1.0, 0.0),  // Top face (1)
+    Vec3::new(1.0, 0.0, 0.0),  // Right face (2)
+    Vec3::new(0.0, 0.0, -1.0), // Back face (3)
+    Vec3::new(0.0, 0.0, 1.0),  // Front face (4)
+    Vec3::new(-1.0, 0.0, 0.0), // Left face (5)
+    Vec3::new(0.0, -1.0, 0.0), // Bottom face (6)
+];
+
+/// The polyhedron a die is shaped as.
+#[derive(
+    Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize,
+)]
+#[reflect(Component)]
+pub enum DieKind {
+    D4,
+    #[default]
+    D6,
+    D8,
+    D10,
+    D12,
+    D20,
+}
+
+impl DieKind {
+    pub const ALL: [DieKind; 6] = [
+        DieKind::D4,
+        DieKind::D6,
+        DieKind::D8,
+        DieKind::D10,
+        DieKind::D12,
+        DieKind::D20,
+    ];
+
+    /// The number of faces, which is also the highest value the die can roll.
+    pub fn sides(self) -> u32 {
+        match self {
+            DieKind::D4 => 4,
+            DieKind::D6 => 6,
+            DieKind::D8 => 8,
+            DieKind::D10 => 10,
+            DieKind::D12 => 12,
+            DieKind::D20 => 20,
+        }
+    }
+
+    /// The die with the given number of sides, if there is one.
+    pub fn from_sides(sides: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.sides() == sides)
+    }
+
+    /// The direction the face that is read points to when the die rests flat.
+    /// A d4 rests on a face and has a vertex on top, so it is read from the face it lies on.
+    fn read_direction(self) -> Vec3 {
+        match self {
+            DieKind::D4 => Vec3::NEG_Y,
+            _ => Vec3::Y,
+        }
+    }
+
+    fn polyhedron(self) -> &'static Polyhedron {
+        static D4: LazyLock<Polyhedron> = LazyLock::new(|| {
+            let vertices = [
+                Vec3::new(1.0, 1.0, 1.0),
+                Vec3::new(1.0, -1.0, -1.0),
+                Vec3::new(-1.0, 1.0, -1.0),
+                Vec3::new(-1.0, -1.0, 1.0),
+            ];
+            // Each face is opposite one of the vertices.
+            let normals = vertices.map(|vertex| -vertex);
+            Polyhedron::new(&vertices, &normals)
+        });
+        static D6: LazyLock<Polyhedron> = LazyLock::new(|| {
+            let mut vertices = Vec::new();
+            for x in [-1.0, 1.0] {
+                for y in [-1.0, 1.0] {
+                    for z in [-1.0, 1.0] {
+                        vertices.push(Vec3::new(x, y, z));
+                    }
+                }
+            }
+            Polyhedron::new(&vertices, &CUBE_SIDES)
+        });
+        static D8: LazyLock<Polyhedron> = LazyLock::new(|| {
+            let vertices = [
+                Vec3::X,
+                Vec3::NEG_X,
+                Vec3::Y,
+                Vec3::NEG_Y,
+                Vec3::Z,
+                Vec3::NEG_Z,
+            ];
+            let mut normals = Vec::new();
+            for y in [1.0, -1.0] {
+                for x in [1.0, -1.0] {
+                    for z in [1.0, -1.0] {
+                        normals.push(Vec3::new(x, y, z));
+                    }
+                }
+            }
+            Polyhedron::new(&vertices, &normals)
+        });
+        static D10: LazyLock<Polyhedron> = LazyLock::new(|| {
+            // A pentagonal trapezohedron: two apexes joined by a zig-zag ring of ten vertices.
+            // The apex height keeps every kite-shaped face planar.
+            let ring = 0.1;
+            let cos = (PI / 5.0).cos();
+            let apex = ring * (1.0 + cos) / (1.0 - cos);
+
+            let mut vertices = vec![Vec3::new(0.0, apex, 0.0), Vec3::new(0.0, -apex, 0.0)];
+            for i in 0..10 {
+                let angle = TAU * i as f32 / 10.0;
+                let height = if i % 2 == 0 { ring } else { -ring };
+                vertices.push(Vec3::new(angle.cos(), height, angle.sin()));
+            }
+
+            let mut normals = Vec::new();
+            for i in 0..5 {
+                let angle = TAU * (i as f32 + 0.5) / 5.0;
+                normals.push(Vec3::new(
+                    angle.cos() * (ring + apex),
+                    1.0,
+                    angle.sin() * (ring + apex),
+                ));
+                let opposite = angle + PI;
+                normals.push(Vec3::new(
+                    opposite.cos() * (ring + apex),
+                    -1.0,
+                    opposite.sin() * (ring + apex),
+                ));
+            }
+            Polyhedron::new(&vertices, &normals)
+        });
+        static D12: LazyLock<Polyhedron> = LazyLock::new(|| {
+            // The faces of a dodecahedron point to the vertices of an icosahedron, and vice versa.
+            Polyhedron::new(&dodecahedron_vertices(), &icosahedron_vertices())
+        });
+        static D20: LazyLock<Polyhedron> =
+            LazyLock::new(|| Polyhedron::new(&icosahedron_vertices(), &dodecahedron_vertices()));
+
+        match self {
+            DieKind::D4 => &D4,
+            DieKind::D6 => &D6,
+            DieKind::D8 => &D8,
+            DieKind::D10 => &D10,
+            DieKind::D12 => &D12,
+            DieKind::D20 => &D20,
+        }
+    }
+
+    /// The outward normal and value of every face, in the die's local space.
+    pub fn faces(self) -> &'static [(Vec3, u32)] {
+        &self.polyhedron().faces
+    }
+
+    /// A collider matching the shape of the die.
+    pub fn collider(self) -> Collider {
+        match self {
+            DieKind::D6 => Collider::cuboid(0.5, 0.5, 0.5),
+            _ => Collider::convex_hull(self.polyhedron().vertices.clone())
+                .expect("die vertices should form a convex hull"),
+        }
+    }
+
+    /// A flat-shaded mesh of the die, for kinds without a model of their own.
+    pub fn mesh(self) -> Mesh {
+        self.polyhedron().mesh()
+    }
+
+    /// Returns the rotation that rests the die flat on the face with the given value,
+    /// keeping its heading. Returns `rotation` unchanged if the die has no such face.
+    pub fn turn_to_face(self, rotation: Quat, face: u32) -> Quat {
+        let Some(&(normal, _)) = self.faces().iter().find(|&&(_, value)| value == face) else {
+            return rotation;
+        };
+        let normal = rotation.mul_vec3(normal);
+        (Quat::from_rotation_arc(normal, self.read_direction()) * rotation).normalize()
+    }
+
+    /// A center of mass that makes the die favor landing on the given face, moved off
+    /// center by `bias`, from 0 for a fair die to 1 for all the way to the opposite face.
+    /// Returns `None` if the die has no such face.
+    pub fn loaded_center_of_mass(self, face: u32, bias: f32) -> Option<Vec3> {
+        let polyhedron = self.polyhedron();
+        let &(normal, _) = polyhedron.faces.iter().find(|&&(_, value)| value == face)?;
+        let depth = polyhedron
+            .vertices
+            .iter()
+            .map(|v| v.dot(normal))
+            .fold(f32::NEG_INFINITY, f32::max);
+        // The weight settles at the bottom, and the face on the other side is read.
+        Some(-normal * self.read_direction().y * bias.clamp(0.0, 1.0) * depth)
+    }
+
+    /// Where the marking of each face goes, in the die's local space.
+    pub fn face_decals(self) -> Vec<FaceDecal> {
+        self.polyhedron().face_decals()
+    }
+
+    /// Returns the value of the face being read for a die with the given rotation.
+    pub fn face_value(self, rotation: Quat) -> u32 {
+        self.best_face(rotation).2
+    }
+
+    /// Determines if a die with the given rotation is resting on an edge or corner
+    /// rather than flat on a face.
+    ///
+    /// `tolerance` is the fraction of the tilt towards the next face that is still
+    /// considered flat, from 0 to 1.
+    pub fn is_cocked(self, rotation: Quat, tolerance: f32) -> bool {
+        let (alignment, _, _) = self.best_face(rotation);
+        let tilt = alignment.clamp(-1.0, 1.0).acos();
+        tilt > tolerance * self.polyhedron().adjacent_angle / 2.0
+    }
+
+    /// Returns the rotation closest to `rotation` that rests the die flat on the face
+    /// being read.
+    pub fn snap_rotation(self, rotation: Quat) -> Quat {
+        let (_, normal, _) = self.best_face(rotation);
+        (Quat::from_rotation_arc(normal, self.read_direction()) * rotation).normalize()
+    }
+
+    /// The alignment with [`Self::read_direction`], rotated normal and value of the face
+    /// closest to it.
+    fn best_face(self, rotation: Quat) -> (f32, Vec3, u32) {
+        let direction = self.read_direction();
+        self.faces()
+            .iter()
+            .map(|&(normal, value)| {
+                let normal = rotation.mul_vec3(normal);
+                (normal.dot(direction), normal, value)
+            })
+            .fold((f32::NEG_INFINITY, Vec3::ZERO, 0), |best, face| {
+                if face.0 > best.0 { face } else { best }
+            })
+    }
+}
+
+/// The spot on a face of a die where its value is marked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FaceDecal {
+    pub value: u32,
+    pub center: Vec3,
+    /// The outward normal of the face.
+    pub normal: Vec3,
+    /// Radius of the largest circle around the center that fits on the face.
+    pub radius: f32,
+}
+
+/// The geometry of a die, scaled to [`RADIUS`].
+struct Polyhedron {
+    vertices: Vec<Vec3>,
+    faces: Vec<(Vec3, u32)>,
+    /// The smallest angle between the normals of two faces.
+    adjacent_angle: f32,
+}
+
+impl Polyhedron {
+    /// Builds the face table from the given normals.
+    /// Values are handed out in order, and opposite faces add up to one more than the
+    /// number of sides, as on real dice.
+    fn new(vertices: &[Vec3], normals: &[Vec3]) -> Self {
+        let scale = RADIUS / vertices.iter().map(|v| v.length()).fold(0.0, f32::max);
+        let vertices = vertices.iter().map(|&v| v * scale).collect();
+
+        let normals: Vec<Vec3> = normals.iter().map(|n| n.normalize()).collect();
+        let sides = normals.len() as u32;
+        let mut values = vec![0; normals.len()];
+        let mut next = 1;
+        for i in 0..normals.len() {
+            if values[i] != 0 {
+                continue;
+            }
+            values[i] = next;
+            if let Some(j) = normals.iter().position(|&n| n.dot(normals[i]) < -0.999) {
+                values[j] = sides + 1 - next;
+            }
+            next += 1;
+        }
+
+        let mut adjacent_angle = PI;
+        for (i, a) in normals.iter().enumerate() {
+            for b in &normals[i + 1..] {
+                adjacent_angle = adjacent_angle.min(a.angle_between(*b));
+            }
+        }
+
+        Self {
+            vertices,
+            faces: normals.into_iter().zip(values).collect(),
+            adjacent_angle,
+        }
+    }
+
+    /// The corners of the face with the given normal, wound counter-clockwise around it.
+    fn face_corners(&self, normal: Vec3) -> Vec<Vec3> {
+        let depth = self
+            .vertices
+            .iter()
+            .map(|v| v.dot(normal))
+            .fold(f32::NEG_INFINITY, f32::max);
+        let mut corners: Vec<Vec3> = self
+            .vertices
+            .iter()
+            .copied()
+            .filter(|v| (v.dot(normal) - depth).abs() < 1e-4)
+            .collect();
+
+        let center = corners.iter().sum::<Vec3>() / corners.len() as f32;
+        let (u, w) = normal.any_orthonormal_pair();
+        corners.sort_by(|a, b| {
+            let a = (*a - center).dot(w).atan2((*a - center).dot(u));
+            let b = (*b - center).dot(w).atan2((*b - center).dot(u));
+            a.total_cmp(&b)
+        });
+        if (corners[1] - corners[0])
+            .cross(corners[2] - corners[0])
+            .dot(normal)
+            < 0.0
+        {
+            corners.reverse();
+        }
+        corners
+    }
+
+    fn face_decals(&self) -> Vec<FaceDecal> {
+        self.faces
+            .iter()
+            .map(|&(normal, value)| {
+                let corners = self.face_corners(normal);
+                let center = corners.iter().sum::<Vec3>() / corners.len() as f32;
+                let radius = corners
+                    .iter()
+                    .zip(corners.iter().cycle().skip(1))
+                    .map(|(&a, &b)| {
+                        let edge = (b - a).normalize();
+                        let offset = center - a;
+                        (offset - edge * offset.dot(edge)).length()
+                    })
+                    .fold(f32::INFINITY, f32::min);
+                FaceDecal {
+                    value,
+                    center,
+                    normal,
+                    radius,
+                }
+            })
+            .collect()
+    }
+
+    fn mesh(&self) -> Mesh {
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut indices = Vec::new();
+
+        for &(normal, _) in &self.faces {
+            let corners = self.face_corners(normal);
+            let first = positions.len() as u32;
+            for i in 1..corners.len() as u32 - 1 {
+                indices.extend([first, first + i, first + i + 1]);
+            }
+            normals.extend(std::iter::repeat_n(normal, corners.len()));
+            positions.extend(corners);
+        }
+
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_indices(Indices::U32(indices))
+    }
+}
+
+fn icosahedron_vertices() -> Vec<Vec3> {
+    let mut vertices = Vec::new();
+    for a in [1.0, -1.0] {
+        for b in [PHI, -PHI] {
+            vertices.push(Vec3::new(0.0, b, a));
+            vertices.push(Vec3::new(b, a, 0.0));
+            vertices.push(Vec3::new(a, 0.0, b));
+        }
+    }
+    vertices
+}
+
+fn dodecahedron_vertices() -> Vec<Vec3> {
+    let mut vertices = Vec::new();
+    for x in [1.0, -1.0] {
+        for y in [1.0, -1.0] {
+            for z in [1.0, -1.0] {
+                vertices.push(Vec3::new(x, y, z));
+            }
+        }
+    }
+    for a in [1.0 / PHI, -1.0 / PHI] {
+        for b in [PHI, -PHI] {
+            vertices.push(Vec3::new(0.0, a, b));
+            vertices.push(Vec3::new(a, b, 0.0));
+            vertices.push(Vec3::new(b, 0.0, a));
+        }
+    }
+    vertices
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn d6_reads_each_cube_side() {
+        let orientations = [
+            (Quat::IDENTITY, 1),
+            (Quat::from_rotation_z(FRAC_PI_2), 2),
+            (Quat::from_rotation_x(FRAC_PI_2), 3),
+            (Quat::from_rotation_x(-FRAC_PI_2), 4),
+            (Quat::from_rotation_z(-FRAC_PI_2), 5),
+            (Quat::from_rotation_x(PI), 6),
+        ];
+
+        for (rotation, face) in orientations {
+            assert_eq!(
+                DieKind::D6.face_value(rotation),
+                face,
+                "rotation {rotation:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn d6_ignores_spin_about_vertical_axis() {
+        let spin = Quat::from_rotation_y(1.2);
+        assert_eq!(
+            DieKind::D6.face_value(spin * Quat::from_rotation_z(FRAC_PI_2)),
+            2
+        );
+        assert_eq!(DieKind::D6.face_value(spin * Quat::from_rotation_x(PI)), 6);
+    }
+
+    #[test]
+    fn every_kind_has_one_face_per_value() {
+        for kind in DieKind::ALL {
+            let mut values: Vec<u32> = kind.faces().iter().map(|&(_, value)| value).collect();
+            values.sort();
+            assert_eq!(values, (1..=kind.sides()).collect::<Vec<_>>(), "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn every_face_reads_when_resting_flat() {
+        for kind in DieKind::ALL {
+            for &(normal, value) in kind.faces() {
+                let rotation = Quat::from_rotation_arc(normal, kind.read_direction());
+                assert_eq!(kind.face_value(rotation), value, "{kind:?}");
+                assert!(!kind.is_cocked(rotation, 0.4), "{kind:?} face {value}");
+            }
+        }
+    }
+
+    #[test]
+    fn resting_on_an_edge_is_cocked() {
+        for kind in DieKind::ALL {
+            let faces = kind.faces();
+            let (a, _) = faces[0];
+            let (b, _) = faces
+                .iter()
+                .skip(1)
+                .copied()
+                .max_by(|(x, _), (y, _)| x.dot(a).total_cmp(&y.dot(a)))
+                .unwrap();
+            let edge = (a + b).normalize();
+            let rotation = Quat::from_rotation_arc(edge, kind.read_direction());
+            assert!(kind.is_cocked(rotation, 0.4), "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn turning_to_a_face_reads_it() {
+        let rotation = Quat::from_rotation_x(0.3) * Quat::from_rotation_y(1.1);
+        for kind in DieKind::ALL {
+            for value in 1..=kind.sides() {
+                let turned = kind.turn_to_face(rotation, value);
+                assert_eq!(kind.face_value(turned), value, "{kind:?}");
+                assert!(!kind.is_cocked(turned, 0.01), "{kind:?} face {value}");
+            }
+        }
+    }
+
+    #[test]
+    fn loaded_weight_sits_below_the_favored_face() {
+        for kind in DieKind::ALL {
+            for &(normal, value) in kind.faces() {
+                let center_of_mass = kind.loaded_center_of_mass(value, 0.5).unwrap();
+                let resting = Quat::from_rotation_arc(normal, kind.read_direction());
+                assert!((resting * center_of_mass).y < 0.0, "{kind:?} face {value}");
+            }
+            assert_eq!(kind.loaded_center_of_mass(kind.sides() + 1, 0.5), None);
+        }
+    }
+
+    #[test]
+    fn d6_decals_sit_on_the_cube_sides() {
+        let decals = DieKind::D6.face_decals();
+        assert_eq!(decals.len(), 6);
+        let half_size = RADIUS / 3.0_f32.sqrt();
+        for decal in decals {
+            assert!(
+                (decal.center - decal.normal * half_size).length() < 1e-5,
+                "{decal:?}"
+            );
+            assert!((decal.radius - half_size).abs() < 1e-5, "{decal:?}");
+        }
+    }
+
+    #[test]
+    fn snapping_keeps_the_face_and_rests_flat() {
+        for kind in DieKind::ALL {
+            let (normal, value) = kind.faces()[0];
+            let tilt = Quat::from_rotation_arc(normal, kind.read_direction());
+            let rotation = Quat::from_rotation_x(0.2) * Quat::from_rotation_z(-0.15) * tilt;
+            let snapped = kind.snap_rotation(rotation);
+            assert_eq!(kind.face_value(snapped), value, "{kind:?}");
+            assert!(!kind.is_cocked(snapped, 0.01), "{kind:?}");
+        }
+    }
+}
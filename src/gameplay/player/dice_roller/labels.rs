@@ -0,0 +1,211 @@
+//! Labels showing the result of a roll: the face of each settled die, floating above it on
+//! screen, and the total of the pool counting up once every die has settled.
+
+use std::time::Duration;
+
+use bevy::{prelude::*, ui::UiSystem};
+
+use super::{
+    Die, DieRolled, DieState,
+    hold::Held,
+    pool::{DicePool, DicePoolRolled},
+    replay::DiceThrown,
+};
+use crate::{gameplay::player::Player, screens::Screen, theme::prelude::*};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            clear_roll_labels,
+            label_settled_dice,
+            label_pool_total,
+            count_up_pool_total,
+        )
+            .chain()
+            .run_if(in_state(Screen::Gameplay)),
+    );
+    app.add_systems(
+        PostUpdate,
+        follow_dice
+            .before(UiSystem::Layout)
+            .run_if(in_state(Screen::Gameplay)),
+    );
+}
+
+/// How high above the center of a die its label floats.
+const LABEL_HEIGHT: f32 = 0.8;
+/// How long the total of a pool takes to count up to its value.
+const COUNT_UP_DURATION: Duration = Duration::from_millis(600);
+
+/// A label showing the face of a settled die, kept over the die on screen.
+#[derive(Component, Debug)]
+struct DieLabel {
+    die: Entity,
+}
+
+/// A label showing the total of the latest pool roll, counting up to it.
+#[derive(Component, Debug, Clone, PartialEq)]
+struct PoolTotalLabel {
+    expression: String,
+    total: i64,
+    /// When the pool finished rolling.
+    shown_at: Duration,
+}
+
+impl PoolTotalLabel {
+    /// The value to show at the given time, easing out from zero to the total.
+    fn value(&self, now: Duration) -> i64 {
+        let progress = (now.saturating_sub(self.shown_at).as_secs_f32()
+            / COUNT_UP_DURATION.as_secs_f32())
+        .min(1.0);
+        let eased = 1.0 - (1.0 - progress).powi(3);
+        (self.total as f32 * eased).round() as i64
+    }
+}
+
+/// Removes the labels of the previous roll when the next one starts, except over held dice
+fn clear_roll_labels(
+    mut commands: Commands,
+    mut thrown: EventReader<DiceThrown>,
+    die_labels: Query<(Entity, &DieLabel)>,
+    total_labels: Query<Entity, With<PoolTotalLabel>>,
+    held: Query<(), With<Held>>,
+) {
+    if thrown.read().count() == 0 {
+        return;
+    }
+    for (entity, label) in &die_labels {
+        if !held.contains(label.die) {
+            commands.entity(entity).despawn();
+        }
+    }
+    for entity in &total_labels {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Shows the face of each die that comes to rest, replacing its previous label
+fn label_settled_dice(
+    mut commands: Commands,
+    mut die_rolled: EventReader<DieRolled>,
+    mut die_labels: Query<(&DieLabel, &mut Text)>,
+) {
+    for rolled in die_rolled.read() {
+        if let Some((_, mut text)) = die_labels
+            .iter_mut()
+            .find(|(label, _)| label.die == rolled.entity)
+        {
+            text.0 = rolled.face.to_string();
+            continue;
+        }
+
+        commands.spawn((
+            widget::label(rolled.face.to_string()),
+            DieLabel { die: rolled.entity },
+            Node {
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            // Hidden until it has been placed over its die.
+            Visibility::Hidden,
+            Pickable::IGNORE,
+            StateScoped(Screen::Gameplay),
+        ));
+    }
+}
+
+/// Shows the total of the pool once every die has settled
+fn label_pool_total(
+    mut commands: Commands,
+    time: Res<Time>,
+    pool: Res<DicePool>,
+    mut pool_rolled: EventReader<DicePoolRolled>,
+    total_labels: Query<Entity, With<PoolTotalLabel>>,
+) {
+    let Some(rolled) = pool_rolled.read().last() else {
+        return;
+    };
+    for entity in &total_labels {
+        commands.entity(entity).despawn();
+    }
+
+    commands.spawn((
+        Name::new("Pool Total"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        Pickable::IGNORE,
+        StateScoped(Screen::Gameplay),
+        children![(
+            widget::header(""),
+            PoolTotalLabel {
+                expression: pool.expression().to_string(),
+                total: rolled.total,
+                shown_at: time.elapsed(),
+            },
+        )],
+    ));
+}
+
+fn count_up_pool_total(time: Res<Time>, mut labels: Query<(&PoolTotalLabel, &mut Text)>) {
+    for (label, mut text) in &mut labels {
+        text.0 = format!("{}: {}", label.expression, label.value(time.elapsed()));
+    }
+}
+
+/// Keeps each die label over its die on screen, hiding it while the die is moving
+fn follow_dice(
+    mut commands: Commands,
+    camera: Single<(&Camera, &GlobalTransform), With<Player>>,
+    dice: Query<(&Die, &GlobalTransform)>,
+    mut labels: Query<(Entity, &DieLabel, &mut Node, &mut Visibility, &ComputedNode)>,
+) {
+    let (camera, camera_transform) = *camera;
+    for (entity, label, mut node, mut visibility, computed) in &mut labels {
+        let Ok((die, die_transform)) = dice.get(label.die) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        let anchor = die_transform.translation() + Vec3::Y * LABEL_HEIGHT;
+        let position = camera
+            .world_to_viewport(camera_transform, anchor)
+            .ok()
+            .filter(|_| die.state == DieState::Stationary);
+        let Some(position) = position else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        // Center the label on the die.
+        let size = computed.size() * computed.inverse_scale_factor();
+        node.left = Val::Px(position.x - size.x / 2.0);
+        node.top = Val::Px(position.y - size.y / 2.0);
+        *visibility = Visibility::Inherited;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn total_counts_up_to_the_roll() {
+        let label = PoolTotalLabel {
+            expression: "3d6".to_string(),
+            total: 12,
+            shown_at: Duration::from_secs(2),
+        };
+        assert_eq!(label.value(Duration::from_secs(2)), 0);
+
+        let halfway = label.value(Duration::from_secs(2) + COUNT_UP_DURATION / 2);
+        assert!(halfway > 6 && halfway < 12, "{halfway}");
+
+        assert_eq!(label.value(Duration::from_secs(2) + COUNT_UP_DURATION), 12);
+        assert_eq!(label.value(Duration::from_secs(10)), 12);
+    }
+}
@@ -0,0 +1,371 @@
+//! Loaded dice, which favor one of their faces, for cursed and blessed dice.
+//!
+//! A [`LoadedDie`] is either weighted, with its center of mass moved away from the favored
+//! face so that physics does the cheating, or overrides its result outright. Overriding
+//! turns the die over after it settles, so the outcome no longer follows the physics.
+//!
+//! Gameplay loads the dice of the pool through [`DiceLoads`], such as when a curse or a
+//! blessing strikes the player.
+//!
+//! In dev builds, press [`READOUT_KEY`] to show how often each loaded die lands on each face,
+//! and to curse or bless the dice of the pool.
+
+use avian3d::prelude::*;
+#[cfg(feature = "dev")]
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::{platform::collections::HashMap, prelude::*};
+#[cfg(feature = "dev")]
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use rand::Rng;
+
+#[cfg(feature = "dev")]
+use super::history::ChiSquare;
+use super::{DieRolled, kind::DieKind, pool::DicePool};
+use crate::screens::Screen;
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<LoadedDie>();
+    app.register_type::<DiceLoads>();
+    app.init_resource::<DiceLoads>();
+    app.init_resource::<LoadedDiceStats>();
+
+    app.add_observer(unload_die);
+    app.add_systems(
+        Update,
+        (
+            load_pool_dice.run_if(in_state(Screen::Gameplay)),
+            weigh_loaded_dice.after(load_pool_dice),
+            count_loaded_faces,
+        ),
+    );
+    app.add_systems(OnExit(Screen::Gameplay), forget_loaded_dice);
+
+    #[cfg(feature = "dev")]
+    {
+        app.init_resource::<LoadedDiceReadout>();
+        app.add_systems(
+            Update,
+            toggle_readout.run_if(in_state(Screen::Gameplay).and(input_just_pressed(READOUT_KEY))),
+        );
+        app.add_systems(
+            EguiPrimaryContextPass,
+            draw_readout
+                .run_if(in_state(Screen::Gameplay).and(resource_equals(LoadedDiceReadout(true)))),
+        );
+    }
+}
+
+#[cfg(feature = "dev")]
+const READOUT_KEY: KeyCode = KeyCode::F9;
+/// How strongly the readout curses or blesses a die.
+#[cfg(feature = "dev")]
+const READOUT_BIAS: f32 = 0.5;
+
+/// A die that favors one of its faces.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq)]
+#[reflect(Component)]
+pub struct LoadedDie {
+    /// The value of the favored face.
+    pub face: u32,
+    /// How strongly the face is favored, from 0 for a fair die to 1.
+    pub bias: f32,
+    pub mode: LoadMode,
+}
+
+/// How a [`LoadedDie`] cheats.
+#[derive(Reflect, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LoadMode {
+    /// The center of mass is moved away from the favored face by the bias, towards the
+    /// opposite face. The outcome still follows the physics.
+    #[default]
+    Weighted,
+    /// The settled die is turned over to the favored face with the bias as probability.
+    /// The outcome doesn't follow the physics, so rolls of this die are flagged.
+    Override,
+}
+
+impl LoadedDie {
+    /// A die weighted towards its lowest face.
+    pub fn cursed(bias: f32) -> Self {
+        Self {
+            face: 1,
+            bias,
+            mode: LoadMode::Weighted,
+        }
+    }
+
+    /// A die weighted towards its highest face.
+    pub fn blessed(kind: DieKind, bias: f32) -> Self {
+        Self {
+            face: kind.sides(),
+            bias,
+            mode: LoadMode::Weighted,
+        }
+    }
+
+    /// The face to turn a settled die over to instead of the given one, if it overrides its
+    /// results and the draw from `rng` says so.
+    pub fn override_face(&self, face: u32, rng: &mut impl Rng) -> Option<u32> {
+        if self.mode != LoadMode::Override {
+            return None;
+        }
+        // Always draw, so that the rest of the roll draws the same numbers either way.
+        let overrides = rng.gen_bool(self.bias.clamp(0.0, 1.0) as f64);
+        (overrides && face != self.face).then_some(self.face)
+    }
+}
+
+/// How each die of the [`DicePool`] is loaded, by its position in the pool. Dice past the
+/// end of the list or set to `None` are fair.
+#[derive(Resource, Reflect, Debug, Clone, Default, PartialEq)]
+#[reflect(Resource)]
+pub struct DiceLoads(pub Vec<Option<LoadedDie>>);
+
+impl DiceLoads {
+    /// How the die at the given position in the pool is loaded, if at all.
+    pub fn get(&self, index: usize) -> Option<LoadedDie> {
+        self.0.get(index).copied().flatten()
+    }
+
+    /// Loads the die at the given position in the pool, or makes it fair again with `None`.
+    pub fn set(&mut self, index: usize, loaded: Option<LoadedDie>) {
+        if self.0.len() <= index {
+            self.0.resize(index + 1, None);
+        }
+        self.0[index] = loaded;
+    }
+}
+
+/// Loads the dice of the pool as [`DiceLoads`] says, touching only the dice whose load differs
+fn load_pool_dice(
+    mut commands: Commands,
+    loads: Res<DiceLoads>,
+    pool: Res<DicePool>,
+    dice: Query<Option<&LoadedDie>, With<DieKind>>,
+) {
+    for (index, entity) in pool.entities().into_iter().enumerate() {
+        let Ok(loaded) = dice.get(entity) else {
+            continue;
+        };
+        match (loads.get(index), loaded) {
+            (Some(wanted), Some(&loaded)) if wanted == loaded => {}
+            (Some(wanted), _) => {
+                commands.entity(entity).insert(wanted);
+            }
+            (None, Some(_)) => {
+                commands.entity(entity).remove::<LoadedDie>();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+/// Moves the center of mass of weighted dice whenever their load changes
+fn weigh_loaded_dice(
+    mut commands: Commands,
+    dice: Query<(Entity, &LoadedDie, &DieKind), Changed<LoadedDie>>,
+) {
+    for (entity, loaded, kind) in &dice {
+        let center_of_mass = match loaded.mode {
+            LoadMode::Weighted => kind
+                .loaded_center_of_mass(loaded.face, loaded.bias)
+                .unwrap_or_else(|| {
+                    warn!("A {kind:?} has no face {} to favor.", loaded.face);
+                    Vec3::ZERO
+                }),
+            LoadMode::Override => Vec3::ZERO,
+        };
+        commands
+            .entity(entity)
+            .insert((CenterOfMass(center_of_mass), NoAutoCenterOfMass));
+    }
+}
+
+/// Puts the center of mass of a die back in its middle when it is no longer loaded
+fn unload_die(trigger: Trigger<OnRemove, LoadedDie>, mut commands: Commands) {
+    // The die may be on its way out.
+    commands
+        .entity(trigger.target())
+        .try_insert(CenterOfMass(Vec3::ZERO));
+}
+
+/// How often each loaded die landed on each of its faces.
+#[derive(Resource, Debug, Default)]
+pub struct LoadedDiceStats {
+    counts: HashMap<Entity, Vec<u64>>,
+}
+
+impl LoadedDiceStats {
+    /// Counts a face the given die landed on.
+    pub fn record(&mut self, entity: Entity, kind: DieKind, face: u32) {
+        let counts = self
+            .counts
+            .entry(entity)
+            .or_insert_with(|| vec![0; kind.sides() as usize]);
+        if let Some(count) = counts.get_mut(face as usize - 1) {
+            *count += 1;
+        }
+    }
+
+    /// How often the given die landed on each face, indexed by face value minus one.
+    pub fn face_counts(&self, entity: Entity) -> Option<&[u64]> {
+        self.counts.get(&entity).map(Vec::as_slice)
+    }
+}
+
+/// Forgets the stats of the dice, which are despawned with the gameplay screen
+fn forget_loaded_dice(mut stats: ResMut<LoadedDiceStats>) {
+    *stats = LoadedDiceStats::default();
+}
+
+fn count_loaded_faces(
+    mut die_rolled: EventReader<DieRolled>,
+    mut stats: ResMut<LoadedDiceStats>,
+    dice: Query<&DieKind, With<LoadedDie>>,
+) {
+    for rolled in die_rolled.read() {
+        if let Ok(kind) = dice.get(rolled.entity) {
+            stats.record(rolled.entity, *kind, rolled.face);
+        }
+    }
+}
+
+/// Whether the loaded dice readout is shown.
+#[cfg(feature = "dev")]
+#[derive(Resource, Reflect, Default, PartialEq)]
+#[reflect(Resource)]
+struct LoadedDiceReadout(bool);
+
+#[cfg(feature = "dev")]
+fn toggle_readout(mut readout: ResMut<LoadedDiceReadout>) {
+    readout.0 = !readout.0;
+}
+
+#[cfg(feature = "dev")]
+fn draw_readout(
+    mut ctxs: EguiContexts,
+    stats: Res<LoadedDiceStats>,
+    pool: Res<DicePool>,
+    mut loads: ResMut<DiceLoads>,
+    kinds: Query<&DieKind>,
+    dice: Query<(Entity, &LoadedDie, &DieKind)>,
+) -> Result {
+    let ctx = ctxs.ctx_mut()?;
+    egui::Window::new("Loaded Dice")
+        .default_width(280.0)
+        .show(ctx, |ui| {
+            for (index, entity) in pool.entities().into_iter().enumerate() {
+                let Ok(&kind) = kinds.get(entity) else {
+                    continue;
+                };
+                ui.horizontal(|ui| {
+                    ui.label(format!("Die {} (d{})", index + 1, kind.sides()));
+                    if ui.button("Curse").clicked() {
+                        loads.set(index, Some(LoadedDie::cursed(READOUT_BIAS)));
+                    }
+                    if ui.button("Bless").clicked() {
+                        loads.set(index, Some(LoadedDie::blessed(kind, READOUT_BIAS)));
+                    }
+                    if ui.button("Fair").clicked() {
+                        loads.set(index, None);
+                    }
+                });
+            }
+            ui.separator();
+
+            if dice.is_empty() {
+                ui.label("No die is loaded.");
+            }
+            for (entity, loaded, kind) in &dice {
+                ui.heading(format!(
+                    "d{} {entity}: favors {} by {:.2}",
+                    kind.sides(),
+                    loaded.face,
+                    loaded.bias
+                ));
+                if loaded.mode == LoadMode::Override {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        "Overrides its results, ignoring physics",
+                    );
+                }
+
+                let Some(counts) = stats.face_counts(entity) else {
+                    ui.label("Not rolled yet.");
+                    continue;
+                };
+                let total: u64 = counts.iter().sum();
+                let fair = 1.0 / counts.len() as f32;
+                for (face, &count) in counts.iter().enumerate() {
+                    let frequency = count as f32 / total.max(1) as f32;
+                    ui.add(egui::ProgressBar::new(frequency).text(format!(
+                        "{}: {count} ({:.0}%, fair {:.0}%)",
+                        face + 1,
+                        frequency * 100.0,
+                        fair * 100.0
+                    )));
+                }
+                if let Some(chi_square) = ChiSquare::test(counts) {
+                    ui.label(format!(
+                        "{total} rolls, p = {:.3} that a fair die does this",
+                        chi_square.p_value
+                    ));
+                }
+                ui.separator();
+            }
+        });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    #[test]
+    fn only_override_mode_overrides() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let weighted = LoadedDie {
+            face: 6,
+            bias: 1.0,
+            mode: LoadMode::Weighted,
+        };
+        assert_eq!(weighted.override_face(2, &mut rng), None);
+
+        let overriding = LoadedDie {
+            mode: LoadMode::Override,
+            ..weighted
+        };
+        assert_eq!(overriding.override_face(2, &mut rng), Some(6));
+        assert_eq!(overriding.override_face(6, &mut rng), None);
+
+        let fair = LoadedDie {
+            bias: 0.0,
+            ..overriding
+        };
+        assert_eq!(fair.override_face(2, &mut rng), None);
+    }
+
+    #[test]
+    fn counts_faces_per_die() {
+        let mut stats = LoadedDiceStats::default();
+        let die = Entity::from_raw(3);
+        for face in [6, 6, 1] {
+            stats.record(die, DieKind::D6, face);
+        }
+        assert_eq!(stats.face_counts(die), Some(&[1, 0, 0, 0, 0, 2][..]));
+        assert_eq!(stats.face_counts(Entity::from_raw(4)), None);
+    }
+
+    #[test]
+    fn dice_loads_grow_to_fit() {
+        let mut loads = DiceLoads::default();
+        loads.set(2, Some(LoadedDie::cursed(0.5)));
+        assert_eq!(loads.get(0), None);
+        assert_eq!(loads.get(2), Some(LoadedDie::cursed(0.5)));
+        assert_eq!(loads.get(5), None);
+        loads.set(2, None);
+        assert_eq!(loads.get(2), None);
+    }
+}
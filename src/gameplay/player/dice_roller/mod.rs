@@ -0,0 +1,549 @@
+//! # Dice Roller
+//! Adapted from Erik Horton's implementation.
+//! <https://blog.erikhorton.com/2024/08/25/building-a-bevy-plugin-for-rolling-dice.html>
+
+use avian3d::prelude::*;
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::{PausableSystems, menus::Menu, screens::Screen};
+
+mod clatter;
+mod cocked;
+mod flick;
+mod history;
+mod hold;
+mod kind;
+mod labels;
+mod loaded;
+mod notation;
+mod physics_config;
+mod pool;
+mod replay;
+mod roll_params;
+mod settle;
+#[cfg(test)]
+mod simulation;
+mod skin;
+mod tray;
+
+use clatter::Clatter;
+use cocked::{CockedPolicy, CockedResolution, CockedRules};
+use flick::{Carried, Flick};
+use hold::{Held, Turn, TurnRules};
+use kind::DieKind;
+use loaded::LoadedDie;
+use physics_config::DicePhysicsConfig;
+use pool::{DicePool, collect_pool_results};
+use replay::DiceThrown;
+use roll_params::{DiceRng, RollParams};
+use settle::{SettleConfig, SettleDetector, SettleStatus};
+use skin::DieModel;
+
+pub use replay::replay_roll_file;
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_plugins((
+        clatter::plugin,
+        history::plugin,
+        hold::plugin,
+        labels::plugin,
+        loaded::plugin,
+        physics_config::plugin,
+        pool::plugin,
+        replay::plugin,
+        skin::plugin,
+        tray::plugin,
+    ));
+    app.register_type::<DieKind>();
+    app.add_event::<DieRolled>();
+    app.add_event::<RollRequested>();
+    app.init_resource::<DiceRng>();
+    app.register_type::<SettleConfig>();
+    app.init_resource::<SettleConfig>();
+    app.register_type::<CockedRules>();
+    app.init_resource::<CockedRules>();
+
+    app.add_systems(OnEnter(Screen::Gameplay), spawn_dice);
+
+    app.add_systems(
+        Update,
+        (quick_roll, flick::drop_carried_dice, roll_dice)
+            .chain()
+            .in_set(PausableSystems)
+            .run_if(in_state(Screen::Gameplay).and(in_state(Menu::None))),
+    );
+
+    app.add_systems(
+        Update,
+        (update_die, collect_pool_results, rethrow_exploded_dice)
+            .chain()
+            .in_set(PausableSystems),
+    );
+}
+
+/// Key that rolls the pool without picking up a die.
+const QUICK_ROLL_KEY: KeyCode = KeyCode::Space;
+/// Gamepad button that rolls the pool without picking up a die.
+const QUICK_ROLL_BUTTON: GamepadButton = GamepadButton::South;
+
+/// Enum to represent the current state of the die
+#[derive(Debug, PartialEq, Eq)]
+enum DieState {
+    Rolling,
+    /// Slow enough to be coming to rest, but not still for long enough to read yet.
+    Settling,
+    Stationary,
+    Cocked,
+}
+
+/// Event published once a rolled die has come to rest flat on one of its faces.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DieRolled {
+    /// The die that was rolled.
+    pub entity: Entity,
+    /// The value of the face pointing up, from 1 to the die's number of sides.
+    pub face: u32,
+    /// How the die was brought flat, if it first came to rest cocked.
+    pub cocked: Option<CockedResolution>,
+    /// Whether a loaded die overrode the face it landed on, rather than physics deciding it.
+    pub overridden: bool,
+}
+
+/// Event asking to roll the pool, sent by the quick roll input or by flicking a die.
+#[derive(Event, Debug, Clone, Copy, Default)]
+struct RollRequested {
+    /// The die thrown by hand, if any.
+    flick: Option<Flick>,
+}
+
+/// Component representing a die with its state and settle detector
+#[derive(Component)]
+struct Die {
+    state: DieState,
+    settle: SettleDetector,
+    /// How often the die came to rest cocked during the current throw.
+    cocked: Option<CockedResolution>,
+}
+
+impl Die {
+    /// Forgets how the previous throw went, for a new throw.
+    fn start_throw(&mut self) {
+        self.state = DieState::Rolling;
+        self.settle.reset();
+        self.cocked = None;
+    }
+
+    /// Records that the die came to rest cocked again, and returns the policy to resolve it with.
+    fn record_cocked(&mut self, rules: &CockedRules) -> CockedPolicy {
+        let attempts = self.cocked.map_or(0, |cocked| cocked.attempts) + 1;
+        let policy = rules.policy_for(attempts);
+        self.cocked = Some(CockedResolution { policy, attempts });
+        policy
+    }
+}
+
+/// Spawns the dice of the [`DicePool`] and their associated components in the game world
+fn spawn_dice(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut pool: ResMut<DicePool>,
+    config: Res<DicePhysicsConfig>,
+) {
+    let dice_handle = asset_server.load(GltfAssetLabel::Scene(0).from_asset(skin::DIE_MODEL));
+    let dice_material = materials.add(StandardMaterial {
+        base_color: Color::WHITE,
+        unlit: true,
+        ..Default::default()
+    });
+
+    // Only the cube has a model for now, the other kinds use a generated mesh.
+    let mut kind_meshes = HashMap::new();
+    for (planned, translation) in pool.spawn_positions() {
+        let kind = planned.kind;
+        let mut entity = commands.spawn(die(kind, dice_material.clone(), translation, &config));
+        pool.add_die(entity.id(), planned);
+        entity
+            .observe(hold::toggle_held)
+            .observe(flick::pick_up)
+            .observe(flick::carry)
+            .observe(flick::release);
+        if kind == DieKind::D6 {
+            entity.with_child((
+                Name::new("Die Model"),
+                DieModel,
+                SceneRoot(dice_handle.clone()),
+            ));
+        } else {
+            let mesh = kind_meshes
+                .entry(kind)
+                .or_insert_with(|| meshes.add(kind.mesh()));
+            entity.insert(Mesh3d(mesh.clone()));
+        }
+    }
+}
+
+/// A single physics-driven die resting at the given position
+fn die(
+    kind: DieKind,
+    material: Handle<StandardMaterial>,
+    translation: Vec3,
+    config: &DicePhysicsConfig,
+) -> impl Bundle + use<> {
+    (
+        Transform {
+            translation,
+            scale: Vec3::splat(config.scale),
+            ..Default::default()
+        },
+        MeshMaterial3d(material),
+        RigidBody::Dynamic,
+        kind.collider(),
+        kind,
+        ExternalForce::new(Vec3::ZERO).with_persistence(false),
+        ExternalTorque::new(Vec3::ZERO).with_persistence(false),
+        ExternalImpulse::new(Vec3::ZERO).with_persistence(false),
+        ExternalAngularImpulse::new(Vec3::ZERO).with_persistence(false),
+        config.die_physics(),
+        LinearVelocity::default(),
+        CollisionEventsEnabled,
+        Clatter::default(),
+        Die {
+            state: DieState::Stationary,
+            settle: SettleDetector::default(),
+            cocked: None,
+        },
+        Name::new("Die"),
+        StateScoped(Screen::Gameplay),
+    )
+}
+
+/// Requests a roll when the quick roll key or gamepad button is pressed
+fn quick_roll(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    mut requests: EventWriter<RollRequested>,
+) {
+    if keys.just_pressed(QUICK_ROLL_KEY)
+        || gamepads
+            .iter()
+            .any(|gamepad| gamepad.just_pressed(QUICK_ROLL_BUTTON))
+    {
+        requests.write(RollRequested::default());
+    }
+}
+
+/// Why the pool can't be rolled right now, if it can't.
+fn roll_blocked(
+    pool: &DicePool,
+    turn: &Turn,
+    turn_rules: &TurnRules,
+    held: &[Entity],
+) -> Option<&'static str> {
+    if pool.is_rolling() {
+        Some("The dice are still rolling!")
+    } else if !turn.can_roll(turn_rules) {
+        Some("No rolls left this turn! Press Enter to start a new turn.")
+    } else if pool.entities().iter().all(|entity| held.contains(entity)) {
+        Some("Every die is held!")
+    } else {
+        None
+    }
+}
+
+/// Rolls every die of the pool that isn't held together when a roll is requested, unless
+/// a roll is still in progress or the turn is out of rolls
+fn roll_dice(
+    mut requests: EventReader<RollRequested>,
+    time: Res<Time>,
+    mut rng: ResMut<DiceRng>,
+    mut pool: ResMut<DicePool>,
+    config: Res<DicePhysicsConfig>,
+    turn_rules: Res<TurnRules>,
+    mut turn: ResMut<Turn>,
+    mut thrown: EventWriter<DiceThrown>,
+    held: Query<Entity, With<Held>>,
+    mut query: Query<(
+        &mut Die,
+        &mut ExternalForce,
+        &mut ExternalTorque,
+        &mut ExternalImpulse,
+        &mut ExternalAngularImpulse,
+    )>,
+) {
+    let Some(request) = requests.read().last().copied() else {
+        return;
+    };
+    let held: Vec<Entity> = held.iter().collect();
+    if let Some(reason) = roll_blocked(&pool, &turn, &turn_rules, &held) {
+        info!("{reason}");
+        return;
+    }
+
+    let seed = rng.start_roll();
+    info!("Rolling {}! (seed {seed})", pool.expression());
+    let dice = pool.start_roll(seed, time.elapsed(), &held);
+    let dice = throw_dice(&dice, request.flick, &mut rng, &config, &mut query);
+    thrown.write(DiceThrown { seed, dice });
+
+    turn.record_roll();
+    if let Some(rolls_left) = turn.rolls_left(&turn_rules) {
+        info!("{rolls_left} rolls left this turn.");
+    }
+}
+
+/// Throws the dice of the pool that exploded again
+fn rethrow_exploded_dice(
+    mut rng: ResMut<DiceRng>,
+    mut pool: ResMut<DicePool>,
+    config: Res<DicePhysicsConfig>,
+    mut query: Query<(
+        &mut Die,
+        &mut ExternalForce,
+        &mut ExternalTorque,
+        &mut ExternalImpulse,
+        &mut ExternalAngularImpulse,
+    )>,
+) {
+    let dice = pool.start_rethrow();
+    if !dice.is_empty() {
+        info!("{} dice exploded!", dice.len());
+        throw_dice(&dice, None, &mut rng, &config, &mut query);
+    }
+}
+
+/// Throws the given dice with freshly drawn impulses, except for the linear impulse of a
+/// flicked die. Returns the impulses of each die.
+fn throw_dice(
+    dice: &[Entity],
+    flick: Option<Flick>,
+    rng: &mut DiceRng,
+    config: &DicePhysicsConfig,
+    query: &mut Query<(
+        &mut Die,
+        &mut ExternalForce,
+        &mut ExternalTorque,
+        &mut ExternalImpulse,
+        &mut ExternalAngularImpulse,
+    )>,
+) -> Vec<(Entity, RollParams)> {
+    let mut thrown = Vec::with_capacity(dice.len());
+    for &entity in dice {
+        let Ok((
+            mut die,
+            mut external_force,
+            mut external_torque,
+            mut external_impulse,
+            mut external_angular_impulse,
+        )) = query.get_mut(entity)
+        else {
+            continue;
+        };
+
+        // Draw the impulses of a flicked die anyway, so that the spin stays random and
+        // replays draw the same numbers.
+        let mut params = RollParams::generate(rng.rng(), &config.throw);
+        if let Some(flick) = flick.filter(|flick| flick.entity == entity) {
+            params.impulse = flick.impulse;
+        }
+        die.start_throw();
+        apply_initial_forces(
+            &params,
+            &mut external_force,
+            &mut external_torque,
+            &mut external_impulse,
+            &mut external_angular_impulse,
+        );
+        thrown.push((entity, params));
+    }
+    thrown
+}
+
+/// Applies initial forces to the die when rolling
+fn apply_initial_forces(
+    params: &RollParams,
+    external_force: &mut ExternalForce,
+    external_torque: &mut ExternalTorque,
+    external_impulse: &mut ExternalImpulse,
+    external_angular_impulse: &mut ExternalAngularImpulse,
+) {
+    external_force.apply_force(Vec3::new(0.0, -19.62, 0.0));
+    external_torque.apply_torque(params.angular_impulse);
+    external_impulse.apply_impulse(params.impulse);
+    external_angular_impulse.apply_impulse(params.angular_impulse);
+}
+
+/// Updates the state of the die based on its velocity and position
+fn update_die(
+    time: Res<Time>,
+    settle_config: Res<SettleConfig>,
+    cocked_rules: Res<CockedRules>,
+    physics_config: Res<DicePhysicsConfig>,
+    mut rng: ResMut<DiceRng>,
+    mut die_rolled: EventWriter<DieRolled>,
+    mut query: Query<
+        (
+            Entity,
+            &mut Die,
+            &DieKind,
+            &mut Transform,
+            &mut ExternalForce,
+            &mut ExternalTorque,
+            &mut ExternalImpulse,
+            &mut ExternalAngularImpulse,
+            &mut RigidBody,
+            &mut LinearVelocity,
+            &mut AngularVelocity,
+            Has<Sleeping>,
+            Option<&LoadedDie>,
+        ),
+        Without<Carried>,
+    >,
+) {
+    for (
+        entity,
+        mut die,
+        kind,
+        mut transform,
+        mut external_force,
+        mut external_torque,
+        mut external_impulse,
+        mut external_angular_impulse,
+        mut rigid_body,
+        mut velocity,
+        mut ang_velocity,
+        sleeping,
+        loaded,
+    ) in query.iter_mut()
+    {
+        let was_stationary = die.state == DieState::Stationary;
+        if matches!(die.state, DieState::Rolling | DieState::Settling) {
+            let status = die.settle.update(
+                &settle_config,
+                time.delta(),
+                velocity.length(),
+                ang_velocity.length(),
+                sleeping,
+            );
+            die.state = match status {
+                SettleStatus::Moving => DieState::Rolling,
+                SettleStatus::Settling => DieState::Settling,
+                SettleStatus::Settled => check_roll_completion(
+                    entity,
+                    &die,
+                    *kind,
+                    loaded,
+                    &mut transform,
+                    &mut rng,
+                    &mut die_rolled,
+                ),
+            };
+        }
+
+        if die.state == DieState::Cocked {
+            let policy = die.record_cocked(&cocked_rules);
+            debug!("The die is cocked! Resolving it with {policy:?}.");
+            die.state = DieState::Rolling;
+            die.settle.reset();
+            match policy {
+                CockedPolicy::Nudge => {
+                    nudge_cocked_die(&mut external_impulse, &mut external_angular_impulse)
+                }
+                CockedPolicy::Reroll => apply_initial_forces(
+                    &RollParams::generate(rng.rng(), &physics_config.throw),
+                    &mut external_force,
+                    &mut external_torque,
+                    &mut external_impulse,
+                    &mut external_angular_impulse,
+                ),
+                CockedPolicy::Snap => {
+                    transform.rotation = kind.snap_rotation(transform.rotation);
+                    velocity.0 = Vec3::ZERO;
+                    ang_velocity.0 = Vec3::ZERO;
+                    die.state = check_roll_completion(
+                        entity,
+                        &die,
+                        *kind,
+                        loaded,
+                        &mut transform,
+                        &mut rng,
+                        &mut die_rolled,
+                    );
+                }
+            }
+        }
+
+        // Only as the die comes to rest, so that a die picked up afterwards stays
+        // kinematic, and resting dice are left alone to fall asleep.
+        if !was_stationary && die.state == DieState::Stationary {
+            display_roll_result(
+                &mut external_force,
+                &mut external_torque,
+                &mut external_impulse,
+                &mut external_angular_impulse,
+                &mut rigid_body,
+            );
+        }
+    }
+}
+
+/// Reads a die that has settled and returns its new state.
+/// Publishes a [`DieRolled`] event when the die came to rest flat, after turning a loaded
+/// die over to its favored face if it overrides its result.
+fn check_roll_completion(
+    entity: Entity,
+    die: &Die,
+    kind: DieKind,
+    loaded: Option<&LoadedDie>,
+    transform: &mut Transform,
+    rng: &mut DiceRng,
+    die_rolled: &mut EventWriter<DieRolled>,
+) -> DieState {
+    let cocked_tolerance = 0.4;
+
+    if kind.is_cocked(transform.rotation, cocked_tolerance) {
+        DieState::Cocked
+    } else {
+        let mut face = kind.face_value(transform.rotation);
+        let overridden = loaded.and_then(|loaded| loaded.override_face(face, rng.rng()));
+        if let Some(favored) = overridden {
+            debug!("The loaded die turns over from {face} to {favored}!");
+            transform.rotation = kind.turn_to_face(transform.rotation, favored);
+            face = favored;
+        }
+        debug!("The die is stationary, showing {face}.");
+        die_rolled.write(DieRolled {
+            entity,
+            face,
+            cocked: die.cocked,
+            overridden: overridden.is_some(),
+        });
+        DieState::Stationary
+    }
+}
+
+/// Gives a cocked die a small push so that it falls flat
+fn nudge_cocked_die(
+    external_impulse: &mut ExternalImpulse,
+    external_angular_impulse: &mut ExternalAngularImpulse,
+) {
+    external_impulse.set_impulse(Vec3::new(0.0, 0.0, 0.0));
+    external_angular_impulse.set_impulse(Vec3::new(-0.15, -0.05, -0.15));
+}
+
+/// Displays the result of the die roll and updates the game state
+fn display_roll_result(
+    external_force: &mut ExternalForce,
+    external_torque: &mut ExternalTorque,
+    external_impulse: &mut ExternalImpulse,
+    external_angular_impulse: &mut ExternalAngularImpulse,
+    rigid_body: &mut RigidBody,
+) {
+    external_force.set_force(Vec3::ZERO);
+    external_torque.set_torque(Vec3::ZERO);
+    external_impulse.set_impulse(Vec3::ZERO);
+    external_angular_impulse.set_impulse(Vec3::ZERO);
+
+    *rigid_body = RigidBody::Dynamic;
+}
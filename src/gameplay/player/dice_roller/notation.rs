@@ -0,0 +1,698 @@
+//! Standard dice notation, such as `3d6+2`, `2d20kh1` or `4d6dl1`.
+//!
+//! An expression is a sum of terms. Each term is either a constant or a group of dice
+//! written `[count]d<sides>`, optionally followed by `!` to explode dice that roll their
+//! highest face, and by one of `kh`/`k`, `kl`, `dh` or `dl`/`d` and a number to keep or
+//! drop the highest or lowest dice of the group.
+
+use std::{fmt, ops::Range, str::FromStr};
+
+use thiserror::Error;
+
+use super::kind::DieKind;
+
+/// The most dice a single group may throw.
+const MAX_DICE: u32 = 50;
+
+/// Byte range of the part of an expression an error refers to.
+pub type Span = Range<usize>;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DiceExprError {
+    #[error("Dice expression is empty")]
+    Empty,
+    #[error("Unexpected character {found:?} at {span:?}")]
+    UnexpectedChar { found: char, span: Span },
+    #[error("Expected {expected} at {span:?}, found the end of the expression")]
+    UnexpectedEnd { expected: &'static str, span: Span },
+    #[error("Number at {span:?} is too large")]
+    NumberTooLarge { span: Span },
+    #[error("There is no die with {sides} sides at {span:?}")]
+    UnsupportedDie { sides: u32, span: Span },
+    #[error("Dice group at {span:?} throws {count} dice, expected 1 to {max}", max = MAX_DICE)]
+    DiceCount { count: u32, span: Span },
+    #[error("Cannot keep or drop {n} of {count} dice at {span:?}")]
+    Selection { n: u32, count: u32, span: Span },
+    #[error("Modifier at {span:?} was already given for this dice group")]
+    DuplicateModifier { span: Span },
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DiceEvalError {
+    #[error("Face {face} is not on a {kind:?}")]
+    InvalidFace { kind: DieKind, face: u32 },
+    #[error("Expected {expected} more faces to finish a throw, got {found}")]
+    FaceCount { expected: usize, found: usize },
+}
+
+/// Which dice of a group count towards its total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    KeepHighest(u32),
+    KeepLowest(u32),
+    DropHighest(u32),
+    DropLowest(u32),
+}
+
+/// A group of identical dice, such as `4d6dl1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiceGroup {
+    pub count: u32,
+    pub kind: DieKind,
+    pub explode: bool,
+    pub selection: Option<Selection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TermValue {
+    Dice(DiceGroup),
+    Constant(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Term {
+    negative: bool,
+    value: TermValue,
+}
+
+/// A parsed dice expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiceExpr {
+    terms: Vec<Term>,
+}
+
+/// A die to throw as part of a [`RollPlan`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlannedDie {
+    pub kind: DieKind,
+    /// Whether rolling the highest face means the die is thrown again.
+    pub explode: bool,
+}
+
+/// The dice to throw together, in order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RollPlan {
+    pub dice: Vec<PlannedDie>,
+}
+
+/// The outcome of one die in a [`RollBreakdown`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DieOutcome {
+    pub face: u32,
+    /// Whether the die counts towards the total, or was dropped.
+    pub kept: bool,
+    /// Whether the die rolled its highest face and exploded into another die.
+    pub exploded: bool,
+    /// Whether the die was thrown because another one exploded.
+    pub from_explosion: bool,
+}
+
+/// The evaluation of a single term.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TermBreakdown {
+    Dice {
+        negative: bool,
+        kind: DieKind,
+        dice: Vec<DieOutcome>,
+        subtotal: i64,
+    },
+    Constant {
+        negative: bool,
+        value: u32,
+    },
+}
+
+/// The full result of an expression, with the outcome of every die.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RollBreakdown {
+    pub terms: Vec<TermBreakdown>,
+    pub total: i64,
+}
+
+/// Progress of evaluating an expression against the faces thrown so far.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Evaluation {
+    /// Some dice exploded, and the given dice have to be thrown before the result is known.
+    NeedsMore(RollPlan),
+    Done(RollBreakdown),
+}
+
+impl DiceExpr {
+    /// The dice of the first throw.
+    pub fn plan(&self) -> RollPlan {
+        let dice = self
+            .groups()
+            .flat_map(|group| std::iter::repeat_n(group.planned(), group.count as usize))
+            .collect();
+        RollPlan { dice }
+    }
+
+    /// Evaluates the expression from the faces of every throw so far, concatenated in the
+    /// order of their [`RollPlan`]s.
+    pub fn evaluate(&self, faces: &[u32]) -> Result<Evaluation, DiceEvalError> {
+        let groups: Vec<&DiceGroup> = self.groups().collect();
+        let mut rolled: Vec<Vec<DieOutcome>> = vec![Vec::new(); groups.len()];
+        let mut batch: Vec<usize> = groups.iter().map(|group| group.count as usize).collect();
+        let mut from_explosion = false;
+        let mut faces = faces.iter().copied();
+
+        loop {
+            let expected = batch.iter().sum();
+            if expected == 0 {
+                break;
+            }
+            let throw: Vec<u32> = faces.by_ref().take(expected).collect();
+            if throw.is_empty() {
+                let dice = groups
+                    .iter()
+                    .zip(&batch)
+                    .flat_map(|(group, &count)| std::iter::repeat_n(group.planned(), count))
+                    .collect();
+                return Ok(Evaluation::NeedsMore(RollPlan { dice }));
+            }
+            if throw.len() < expected {
+                return Err(DiceEvalError::FaceCount {
+                    expected,
+                    found: throw.len(),
+                });
+            }
+
+            let mut throw = throw.into_iter();
+            for (i, group) in groups.iter().enumerate() {
+                let mut explosions = 0;
+                for face in throw.by_ref().take(batch[i]) {
+                    if !(1..=group.kind.sides()).contains(&face) {
+                        return Err(DiceEvalError::InvalidFace {
+                            kind: group.kind,
+                            face,
+                        });
+                    }
+                    let exploded = group.explode && face == group.kind.sides();
+                    if exploded {
+                        explosions += 1;
+                    }
+                    rolled[i].push(DieOutcome {
+                        face,
+                        kept: true,
+                        exploded,
+                        from_explosion,
+                    });
+                }
+                batch[i] = explosions;
+            }
+            from_explosion = true;
+        }
+
+        let mut rolled = rolled.into_iter();
+        let mut total = 0;
+        let terms = self
+            .terms
+            .iter()
+            .map(|term| {
+                let sign = if term.negative { -1 } else { 1 };
+                match &term.value {
+                    TermValue::Constant(value) => {
+                        total += sign * i64::from(*value);
+                        TermBreakdown::Constant {
+                            negative: term.negative,
+                            value: *value,
+                        }
+                    }
+                    TermValue::Dice(group) => {
+                        let mut dice = rolled.next().unwrap_or_default();
+                        group.select(&mut dice);
+                        let subtotal = dice
+                            .iter()
+                            .filter(|die| die.kept)
+                            .map(|die| i64::from(die.face))
+                            .sum::<i64>();
+                        total += sign * subtotal;
+                        TermBreakdown::Dice {
+                            negative: term.negative,
+                            kind: group.kind,
+                            dice,
+                            subtotal,
+                        }
+                    }
+                }
+            })
+            .collect();
+
+        Ok(Evaluation::Done(RollBreakdown { terms, total }))
+    }
+
+    fn groups(&self) -> impl Iterator<Item = &DiceGroup> {
+        self.terms.iter().filter_map(|term| match &term.value {
+            TermValue::Dice(group) => Some(group),
+            TermValue::Constant(_) => None,
+        })
+    }
+}
+
+impl DiceGroup {
+    fn planned(&self) -> PlannedDie {
+        PlannedDie {
+            kind: self.kind,
+            explode: self.explode,
+        }
+    }
+
+    /// Marks the dice that don't count towards the total.
+    fn select(&self, dice: &mut [DieOutcome]) {
+        let Some(selection) = self.selection else {
+            return;
+        };
+
+        // Sort indices from lowest to highest face, keeping throw order for ties.
+        let mut order: Vec<usize> = (0..dice.len()).collect();
+        order.sort_by_key(|&i| dice[i].face);
+
+        let len = dice.len();
+        let dropped = match selection {
+            Selection::KeepHighest(n) => &order[..len.saturating_sub(n as usize)],
+            Selection::KeepLowest(n) => &order[(n as usize).min(len)..],
+            Selection::DropHighest(n) => &order[len.saturating_sub(n as usize)..],
+            Selection::DropLowest(n) => &order[..(n as usize).min(len)],
+        };
+        for &i in dropped {
+            dice[i].kept = false;
+        }
+    }
+}
+
+impl FromStr for DiceExpr {
+    type Err = DiceExprError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser::new(s).parse()
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Self {
+        Self { source, pos: 0 }
+    }
+
+    fn parse(mut self) -> Result<DiceExpr, DiceExprError> {
+        self.skip_whitespace();
+        if self.peek().is_none() {
+            return Err(DiceExprError::Empty);
+        }
+
+        let mut terms = Vec::new();
+        let mut negative = self.eat('-');
+        if !negative {
+            self.eat('+');
+        }
+        loop {
+            self.skip_whitespace();
+            let value = self.term()?;
+            terms.push(Term { negative, value });
+
+            self.skip_whitespace();
+            negative = match self.peek() {
+                None => break,
+                Some('+') => false,
+                Some('-') => true,
+                Some(found) => return Err(self.unexpected(found)),
+            };
+            self.bump();
+        }
+
+        Ok(DiceExpr { terms })
+    }
+
+    fn term(&mut self) -> Result<TermValue, DiceExprError> {
+        let start = self.pos;
+        let count = match self.peek() {
+            Some(c) if c.is_ascii_digit() => Some(self.number()?),
+            Some(c) if c.eq_ignore_ascii_case(&'d') => None,
+            Some(found) => return Err(self.unexpected(found)),
+            None => return Err(self.end("a number or dice")),
+        };
+
+        if !self.eat_ignore_case('d') {
+            // A plain constant.
+            return Ok(TermValue::Constant(count.unwrap_or_default()));
+        }
+
+        let sides_start = self.pos;
+        let sides = match self.peek() {
+            Some(c) if c.is_ascii_digit() => self.number()?,
+            Some(found) => return Err(self.unexpected(found)),
+            None => return Err(self.end("the number of sides")),
+        };
+        let kind = DieKind::from_sides(sides).ok_or(DiceExprError::UnsupportedDie {
+            sides,
+            span: sides_start..self.pos,
+        })?;
+
+        let count = count.unwrap_or(1);
+        if !(1..=MAX_DICE).contains(&count) {
+            return Err(DiceExprError::DiceCount {
+                count,
+                span: start..self.pos,
+            });
+        }
+
+        let mut group = DiceGroup {
+            count,
+            kind,
+            explode: false,
+            selection: None,
+        };
+        loop {
+            let modifier_start = self.pos;
+            if self.eat('!') {
+                if group.explode {
+                    return Err(DiceExprError::DuplicateModifier {
+                        span: modifier_start..self.pos,
+                    });
+                }
+                group.explode = true;
+                continue;
+            }
+
+            let selection: fn(u32) -> Selection = if self.eat_ignore_case('k') {
+                if self.eat_ignore_case('l') {
+                    Selection::KeepLowest
+                } else {
+                    self.eat_ignore_case('h');
+                    Selection::KeepHighest
+                }
+            } else if self.eat_ignore_case('d') {
+                if self.eat_ignore_case('h') {
+                    Selection::DropHighest
+                } else {
+                    self.eat_ignore_case('l');
+                    Selection::DropLowest
+                }
+            } else {
+                break;
+            };
+
+            let n = match self.peek() {
+                Some(c) if c.is_ascii_digit() => self.number()?,
+                _ => 1,
+            };
+            let span = modifier_start..self.pos;
+            if group.selection.is_some() {
+                return Err(DiceExprError::DuplicateModifier { span });
+            }
+            let selection = selection(n);
+            let valid = match selection {
+                Selection::KeepHighest(n) | Selection::KeepLowest(n) => (1..=count).contains(&n),
+                // Dropping none is most likely a typo.
+                Selection::DropHighest(n) | Selection::DropLowest(n) => (1..count).contains(&n),
+            };
+            if !valid {
+                return Err(DiceExprError::Selection { n, count, span });
+            }
+            group.selection = Some(selection);
+        }
+
+        Ok(TermValue::Dice(group))
+    }
+
+    fn number(&mut self) -> Result<u32, DiceExprError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+        }
+        self.source[start..self.pos]
+            .parse()
+            .map_err(|_| DiceExprError::NumberTooLarge {
+                span: start..self.pos,
+            })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) {
+        if let Some(c) = self.peek() {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let matches = self.peek() == Some(expected);
+        if matches {
+            self.bump();
+        }
+        matches
+    }
+
+    fn eat_ignore_case(&mut self, expected: char) -> bool {
+        let matches = self
+            .peek()
+            .is_some_and(|c| c.eq_ignore_ascii_case(&expected));
+        if matches {
+            self.bump();
+        }
+        matches
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn unexpected(&self, found: char) -> DiceExprError {
+        DiceExprError::UnexpectedChar {
+            found,
+            span: self.pos..self.pos + found.len_utf8(),
+        }
+    }
+
+    fn end(&self, expected: &'static str) -> DiceExprError {
+        DiceExprError::UnexpectedEnd {
+            expected,
+            span: self.pos..self.pos,
+        }
+    }
+}
+
+impl fmt::Display for DiceExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, term) in self.terms.iter().enumerate() {
+            match (i, term.negative) {
+                (_, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, false) => write!(f, "+")?,
+            }
+            match &term.value {
+                TermValue::Constant(value) => write!(f, "{value}")?,
+                TermValue::Dice(group) => {
+                    write!(f, "{}d{}", group.count, group.kind.sides())?;
+                    if group.explode {
+                        write!(f, "!")?;
+                    }
+                    match group.selection {
+                        Some(Selection::KeepHighest(n)) => write!(f, "kh{n}")?,
+                        Some(Selection::KeepLowest(n)) => write!(f, "kl{n}")?,
+                        Some(Selection::DropHighest(n)) => write!(f, "dh{n}")?,
+                        Some(Selection::DropLowest(n)) => write!(f, "dl{n}")?,
+                        None => {}
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for RollBreakdown {
+    /// Formats as e.g. `[6!, 3, (1)] + 2 = 11`, with dropped dice in parentheses
+    /// and exploded dice marked with `!`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, term) in self.terms.iter().enumerate() {
+            let negative = match term {
+                TermBreakdown::Dice { negative, .. } | TermBreakdown::Constant { negative, .. } => {
+                    *negative
+                }
+            };
+            match (i, negative) {
+                (0, true) => write!(f, "-")?,
+                (_, true) => write!(f, " - ")?,
+                (0, false) => {}
+                (_, false) => write!(f, " + ")?,
+            }
+            match term {
+                TermBreakdown::Constant { value, .. } => write!(f, "{value}")?,
+                TermBreakdown::Dice { dice, .. } => {
+                    write!(f, "[")?;
+                    for (j, die) in dice.iter().enumerate() {
+                        if j > 0 {
+                            write!(f, ", ")?;
+                        }
+                        let mark = if die.exploded { "!" } else { "" };
+                        if die.kept {
+                            write!(f, "{}{mark}", die.face)?;
+                        } else {
+                            write!(f, "({}{mark})", die.face)?;
+                        }
+                    }
+                    write!(f, "]")?;
+                }
+            }
+        }
+        write!(f, " = {}", self.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn done(expr: &str, faces: &[u32]) -> RollBreakdown {
+        let expr: DiceExpr = expr.parse().unwrap();
+        match expr.evaluate(faces).unwrap() {
+            Evaluation::Done(breakdown) => breakdown,
+            Evaluation::NeedsMore(plan) => panic!("{expr} still needs {plan:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_and_prints_standard_notation() {
+        for (source, printed) in [
+            ("3d6+2", "3d6+2"),
+            ("2d20kh1", "2d20kh1"),
+            ("4d6dl1", "4d6dl1"),
+            ("d20 - 1", "1d20-1"),
+            ("2D8K", "2d8kh1"),
+            ("3d6!", "3d6!"),
+            ("1d4+1d6+1d8", "1d4+1d6+1d8"),
+        ] {
+            let expr: DiceExpr = source.parse().unwrap();
+            assert_eq!(expr.to_string(), printed);
+        }
+    }
+
+    #[test]
+    fn plans_every_die_of_the_first_throw() {
+        let expr: DiceExpr = "2d6+1d20!-3".parse().unwrap();
+        let dice: Vec<_> = expr
+            .plan()
+            .dice
+            .iter()
+            .map(|die| (die.kind, die.explode))
+            .collect();
+        assert_eq!(
+            dice,
+            vec![
+                (DieKind::D6, false),
+                (DieKind::D6, false),
+                (DieKind::D20, true)
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_and_drops_dice() {
+        assert_eq!(done("3d6+2", &[4, 2, 6]).total, 14);
+        assert_eq!(done("2d20kh1", &[7, 15]).total, 15);
+        assert_eq!(done("2d20kl1", &[7, 15]).total, 7);
+        assert_eq!(done("4d6dl1", &[5, 3, 6, 1]).total, 14);
+        assert_eq!(done("4d6dh2", &[5, 3, 6, 1]).total, 4);
+        assert_eq!(
+            done("4d6dl1", &[5, 3, 6, 1]).to_string(),
+            "[5, 3, 6, (1)] = 14"
+        );
+    }
+
+    #[test]
+    fn exploding_dice_ask_for_more_throws() {
+        let expr: DiceExpr = "2d6!+1".parse().unwrap();
+        assert_eq!(
+            expr.evaluate(&[6, 2]).unwrap(),
+            Evaluation::NeedsMore(RollPlan {
+                dice: vec![PlannedDie {
+                    kind: DieKind::D6,
+                    explode: true
+                }]
+            })
+        );
+        let breakdown = done("2d6!+1", &[6, 2, 6, 3]);
+        assert_eq!(breakdown.total, 18);
+        assert_eq!(breakdown.to_string(), "[6!, 2, 6!, 3] + 1 = 18");
+    }
+
+    #[test]
+    fn rejects_faces_that_are_not_on_the_die() {
+        let expr: DiceExpr = "1d6".parse().unwrap();
+        assert_eq!(
+            expr.evaluate(&[7]),
+            Err(DiceEvalError::InvalidFace {
+                kind: DieKind::D6,
+                face: 7
+            })
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_spans() {
+        let cases = [
+            ("", DiceExprError::Empty),
+            (
+                "3d7",
+                DiceExprError::UnsupportedDie {
+                    sides: 7,
+                    span: 2..3,
+                },
+            ),
+            (
+                "2d6kh3",
+                DiceExprError::Selection {
+                    n: 3,
+                    count: 2,
+                    span: 3..6,
+                },
+            ),
+            (
+                "2d6 * 2",
+                DiceExprError::UnexpectedChar {
+                    found: '*',
+                    span: 4..5,
+                },
+            ),
+            (
+                "2d",
+                DiceExprError::UnexpectedEnd {
+                    expected: "the number of sides",
+                    span: 2..2,
+                },
+            ),
+            (
+                "0d6",
+                DiceExprError::DiceCount {
+                    count: 0,
+                    span: 0..3,
+                },
+            ),
+            (
+                "4d6dl0",
+                DiceExprError::Selection {
+                    n: 0,
+                    count: 4,
+                    span: 3..6,
+                },
+            ),
+            ("4d6dl1kh2", DiceExprError::DuplicateModifier { span: 6..9 }),
+            (
+                "99999999999d6",
+                DiceExprError::NumberTooLarge { span: 0..11 },
+            ),
+        ];
+        for (source, error) in cases {
+            assert_eq!(source.parse::<DiceExpr>(), Err(error), "{source:?}");
+        }
+    }
+}
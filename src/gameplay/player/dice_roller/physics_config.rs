@@ -0,0 +1,188 @@
+//! Physics properties of the dice, loaded from `assets/dice.physics.ron` so they can be
+//! tuned without recompiling.
+
+use std::ops::Range;
+
+use avian3d::prelude::*;
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::Die;
+use crate::asset_tracking::LoadResource;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_asset::<DicePhysicsConfig>();
+    app.init_asset_loader::<DicePhysicsConfigLoader>();
+    app.init_resource::<DicePhysicsConfig>();
+    app.load_resource::<DicePhysicsAssets>();
+
+    app.add_systems(Update, apply_physics_config);
+}
+
+/// Physics properties shared by every die.
+#[derive(Asset, Resource, TypePath, Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct DicePhysicsConfig {
+    pub mass: f32,
+    /// Uniform scale of the die's model and collider.
+    pub scale: f32,
+    pub gravity_scale: f32,
+    pub friction: f32,
+    pub restitution: f32,
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    pub throw: ThrowConfig,
+}
+
+impl Default for DicePhysicsConfig {
+    fn default() -> Self {
+        Self {
+            mass: 10.0,
+            scale: 0.8,
+            gravity_scale: 1.0,
+            friction: 0.5,
+            restitution: 0.0,
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            throw: ThrowConfig::default(),
+        }
+    }
+}
+
+impl DicePhysicsConfig {
+    /// The physics components of a die with this configuration.
+    pub fn die_physics(&self) -> impl Bundle + use<> {
+        (
+            Mass(self.mass),
+            GravityScale(self.gravity_scale),
+            Friction::new(self.friction),
+            Restitution::new(self.restitution),
+            LinearDamping(self.linear_damping),
+            AngularDamping(self.angular_damping),
+        )
+    }
+}
+
+/// Ranges the impulses of a throw are drawn from.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ThrowConfig {
+    /// Range of the linear impulse strength applied to a die when it is thrown.
+    pub impulse_strength: Range<f32>,
+    /// Range of the upward tilt of the throw direction, relative to the ground.
+    pub impulse_lift: Range<f32>,
+    /// Range of the angular impulse strength applied to a die when it is thrown.
+    pub spin_strength: Range<f32>,
+}
+
+impl Default for ThrowConfig {
+    fn default() -> Self {
+        Self {
+            impulse_strength: 5.0..9.0,
+            impulse_lift: 0.1..0.3,
+            spin_strength: 6.0..12.0,
+        }
+    }
+}
+
+#[derive(Resource, Asset, Clone, Reflect)]
+#[reflect(Resource)]
+struct DicePhysicsAssets {
+    #[dependency]
+    config: Handle<DicePhysicsConfig>,
+}
+
+impl FromWorld for DicePhysicsAssets {
+    fn from_world(world: &mut World) -> Self {
+        let assets = world.resource::<AssetServer>();
+        Self {
+            config: assets.load("dice.physics.ron"),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+enum DicePhysicsConfigError {
+    #[error("Couldn't read the dice physics config: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Couldn't parse the dice physics config: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+#[derive(Default)]
+struct DicePhysicsConfigLoader;
+
+impl AssetLoader for DicePhysicsConfigLoader {
+    type Asset = DicePhysicsConfig;
+    type Settings = ();
+    type Error = DicePhysicsConfigError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["physics.ron"]
+    }
+}
+
+/// Copies the config into its resource once it is loaded, and again whenever the file
+/// changes while hot reloading, updating the dice already spawned.
+fn apply_physics_config(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<DicePhysicsConfig>>,
+    configs: Res<Assets<DicePhysicsConfig>>,
+    mut config: ResMut<DicePhysicsConfig>,
+    mut dice: Query<(Entity, &mut Transform), With<Die>>,
+) {
+    for event in events.read() {
+        let id = match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => *id,
+            _ => continue,
+        };
+        let Some(loaded) = configs.get(id) else {
+            continue;
+        };
+        if *loaded == *config {
+            continue;
+        }
+
+        info!("Applying dice physics config: {loaded:?}");
+        *config = loaded.clone();
+        for (entity, mut transform) in &mut dice {
+            transform.scale = Vec3::splat(config.scale);
+            commands.entity(entity).insert(config.die_physics());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_config_parses() {
+        let config: DicePhysicsConfig =
+            ron::de::from_bytes(include_bytes!("../../../../assets/dice.physics.ron")).unwrap();
+        assert!(config.mass > 0.0);
+        assert!(!config.throw.impulse_strength.is_empty());
+    }
+
+    #[test]
+    fn missing_fields_keep_their_defaults() {
+        let config: DicePhysicsConfig = ron::de::from_str("(mass: 2.5)").unwrap();
+        assert_eq!(config.mass, 2.5);
+        assert_eq!(config.throw, ThrowConfig::default());
+    }
+}
@@ -0,0 +1,322 @@
+//! A pool of dice that are thrown together, such as 2d6 or 3d6.
+
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::prelude::*;
+
+use super::{
+    DieRolled,
+    kind::DieKind,
+    notation::{DiceExpr, Evaluation, PlannedDie, RollBreakdown},
+};
+use crate::screens::Screen;
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_resource::<DicePool>();
+    app.add_event::<DicePoolRolled>();
+
+    app.add_systems(OnExit(Screen::Gameplay), abandon_roll);
+}
+
+/// Distance of each die from the center of the tray when several are spawned.
+const SPAWN_RADIUS: f32 = 0.6;
+/// Height the dice are spawned at.
+const SPAWN_HEIGHT: f32 = 1.5;
+
+/// The dice the player throws together, described by a dice expression.
+#[derive(Resource, Debug, Clone)]
+pub struct DicePool {
+    expression: DiceExpr,
+    /// The spawned dice, in the order of the expression's plan.
+    dice: Vec<PoolDie>,
+    /// The dice of the throw in progress, with their face once settled.
+    throw: Vec<(Entity, Option<u32>)>,
+    /// Exploded dice that have to be thrown again before the roll is over.
+    rethrow: Vec<Entity>,
+    /// The dice that keep their face during the roll in progress instead of being thrown.
+    held: Vec<Entity>,
+    /// The faces of every throw of the roll in progress.
+    faces: Vec<u32>,
+    /// Every die thrown during the roll in progress, in the order they settled.
+    thrown: Vec<(DieKind, DieRolled)>,
+    /// The seed of the roll in progress.
+    seed: u64,
+    /// When the roll in progress was started.
+    started_at: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PoolDie {
+    entity: Entity,
+    planned: PlannedDie,
+    /// The latest roll of the die, once settled.
+    rolled: Option<DieRolled>,
+}
+
+impl DicePool {
+    pub fn new(expression: DiceExpr) -> Self {
+        Self {
+            expression,
+            dice: Vec::new(),
+            throw: Vec::new(),
+            rethrow: Vec::new(),
+            held: Vec::new(),
+            faces: Vec::new(),
+            thrown: Vec::new(),
+            seed: 0,
+            started_at: Duration::ZERO,
+        }
+    }
+
+    pub fn expression(&self) -> &DiceExpr {
+        &self.expression
+    }
+
+    /// Whether a roll was started and some of its dice have yet to settle.
+    pub fn is_rolling(&self) -> bool {
+        !self.throw.is_empty() || !self.rethrow.is_empty()
+    }
+
+    /// Each die of the expression with its starting position, spread evenly on a circle
+    /// above the tray.
+    pub(super) fn spawn_positions(&self) -> Vec<(PlannedDie, Vec3)> {
+        let dice = self.expression.plan().dice;
+        let count = dice.len();
+        let radius = if count > 1 { SPAWN_RADIUS } else { 0.0 };
+        dice.into_iter()
+            .enumerate()
+            .map(|(i, planned)| {
+                let angle = TAU * i as f32 / count as f32;
+                let translation =
+                    Vec3::new(radius * angle.cos(), SPAWN_HEIGHT, radius * angle.sin());
+                (planned, translation)
+            })
+            .collect()
+    }
+
+    /// The spawned dice, in the order of the expression's plan.
+    pub(super) fn entities(&self) -> Vec<Entity> {
+        self.dice.iter().map(|die| die.entity).collect()
+    }
+
+    /// Whether the given die may keep its latest face through the next roll: it has to have
+    /// settled since the start of the roll, on a face that doesn't explode.
+    pub(super) fn can_hold(&self, entity: Entity) -> bool {
+        self.dice.iter().any(|die| {
+            die.entity == entity
+                && die.rolled.is_some_and(|rolled| {
+                    !(die.planned.explode && rolled.face == die.planned.kind.sides())
+                })
+        })
+    }
+
+    /// Sets the latest face of a die without rolling it, such as for a held die put back
+    /// in place by a replay.
+    pub(super) fn set_face(&mut self, entity: Entity, face: u32) {
+        if let Some(die) = self.dice.iter_mut().find(|die| die.entity == entity) {
+            die.rolled = Some(DieRolled {
+                entity,
+                face,
+                cocked: None,
+                overridden: false,
+            });
+        }
+    }
+
+    /// Adds a spawned die to the pool.
+    pub(super) fn add_die(&mut self, entity: Entity, planned: PlannedDie) {
+        self.dice.push(PoolDie {
+            entity,
+            planned,
+            rolled: None,
+        });
+    }
+
+    /// Starts a new roll from the given seed, at the given time. The `held` dice keep their
+    /// latest face, if they have one, and every other die is thrown.
+    /// Returns the dice to throw.
+    pub(super) fn start_roll(&mut self, seed: u64, now: Duration, held: &[Entity]) -> Vec<Entity> {
+        self.seed = seed;
+        self.started_at = now;
+        self.faces.clear();
+        self.thrown.clear();
+        self.rethrow.clear();
+        self.held = held.to_vec();
+        for die in &mut self.dice {
+            if !held.contains(&die.entity) {
+                die.rolled = None;
+            }
+        }
+        self.throw = self
+            .dice
+            .iter()
+            .map(|die| (die.entity, die.rolled.map(|rolled| rolled.face)))
+            .collect();
+        self.throw
+            .iter()
+            .filter(|(_, face)| face.is_none())
+            .map(|&(entity, _)| entity)
+            .collect()
+    }
+
+    /// Starts throwing the dice that exploded. Returns the dice to throw.
+    pub(super) fn start_rethrow(&mut self) -> Vec<Entity> {
+        let dice = std::mem::take(&mut self.rethrow);
+        self.throw = dice.iter().map(|&entity| (entity, None)).collect();
+        dice
+    }
+
+    /// Stops waiting on the roll in progress and forgets the dice, e.g. when they are despawned.
+    fn abandon_roll(&mut self) {
+        self.dice.clear();
+        self.throw.clear();
+        self.rethrow.clear();
+        self.held.clear();
+        self.faces.clear();
+        self.thrown.clear();
+    }
+
+    /// Records a settled die. Returns the evaluated expression once every die has settled
+    /// and no exploded die is left to throw.
+    pub(super) fn record(&mut self, rolled: DieRolled) -> Option<RollBreakdown> {
+        let slot = self
+            .throw
+            .iter_mut()
+            .find(|(entity, _)| *entity == rolled.entity)?;
+        slot.1 = Some(rolled.face);
+        if let Some(die) = self.dice.iter_mut().find(|die| die.entity == rolled.entity) {
+            die.rolled = Some(rolled);
+            self.thrown.push((die.planned.kind, rolled));
+        }
+
+        if self.throw.iter().any(|(_, face)| face.is_none()) {
+            return None;
+        }
+
+        let throw: Vec<(Entity, u32)> = self
+            .throw
+            .drain(..)
+            .filter_map(|(entity, face)| face.map(|face| (entity, face)))
+            .collect();
+        self.faces.extend(throw.iter().map(|&(_, face)| face));
+
+        match self.expression.evaluate(&self.faces) {
+            Ok(Evaluation::Done(breakdown)) => Some(breakdown),
+            Ok(Evaluation::NeedsMore(_)) => {
+                // Held dice explode with the roll they were held from, not again.
+                self.rethrow = throw
+                    .into_iter()
+                    .filter(|(entity, _)| !self.held.contains(entity))
+                    .filter(|&(entity, face)| {
+                        self.dice.iter().any(|die| {
+                            die.entity == entity
+                                && die.planned.explode
+                                && face == die.planned.kind.sides()
+                        })
+                    })
+                    .map(|(entity, _)| entity)
+                    .collect();
+                None
+            }
+            Err(error) => {
+                error!("Couldn't evaluate {}: {error}", self.expression);
+                None
+            }
+        }
+    }
+
+    /// The latest roll of every die in the pool.
+    fn results(&self) -> Vec<DieRolled> {
+        self.dice.iter().filter_map(|die| die.rolled).collect()
+    }
+}
+
+impl Default for DicePool {
+    fn default() -> Self {
+        Self::new("2d6".parse().expect("default dice expression should parse"))
+    }
+}
+
+/// Event published once every die of a pool roll has come to rest.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct DicePoolRolled {
+    /// The latest roll of each die in the pool.
+    pub dice: Vec<DieRolled>,
+    /// The outcome of every die counted, including exploded, dropped and held ones.
+    pub breakdown: RollBreakdown,
+    /// Every die thrown during the roll, including rethrows of exploded dice, in the order
+    /// they settled. Held dice weren't thrown and are left out.
+    pub thrown: Vec<(DieKind, DieRolled)>,
+    /// The value of the pool's expression.
+    pub total: i64,
+    /// The seed the roll was thrown with.
+    pub seed: u64,
+    /// How long the dice took to settle, from the throw until the last die came to rest.
+    pub settle_time: Duration,
+}
+
+/// Collects [`DieRolled`] events into a single [`DicePoolRolled`] once the whole pool has settled.
+pub(super) fn collect_pool_results(
+    time: Res<Time>,
+    mut pool: ResMut<DicePool>,
+    mut die_rolled: EventReader<DieRolled>,
+    mut pool_rolled: EventWriter<DicePoolRolled>,
+) {
+    for rolled in die_rolled.read() {
+        if let Some(breakdown) = pool.record(*rolled) {
+            info!("{}: {breakdown}", pool.expression());
+            pool_rolled.write(DicePoolRolled {
+                dice: pool.results(),
+                thrown: pool.thrown.clone(),
+                total: breakdown.total,
+                breakdown,
+                seed: pool.seed,
+                settle_time: time.elapsed().saturating_sub(pool.started_at),
+            });
+        }
+    }
+}
+
+fn abandon_roll(mut pool: ResMut<DicePool>) {
+    pool.abandon_roll();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rolled(entity: Entity, face: u32) -> DieRolled {
+        DieRolled {
+            entity,
+            face,
+            cocked: None,
+            overridden: false,
+        }
+    }
+
+    #[test]
+    fn dice_on_an_exploding_face_cannot_be_held() {
+        let mut pool = DicePool::new("2d6!".parse().unwrap());
+        let (first, second) = (Entity::from_raw(1), Entity::from_raw(2));
+        for (entity, planned) in [first, second].into_iter().zip(pool.expression.plan().dice) {
+            pool.add_die(entity, planned);
+        }
+
+        assert_eq!(pool.start_roll(0, Duration::ZERO, &[]), vec![first, second]);
+        assert_eq!(pool.record(rolled(first, 6)), None);
+        assert_eq!(pool.record(rolled(second, 2)), None);
+        assert!(!pool.can_hold(first));
+        assert!(pool.can_hold(second));
+
+        assert_eq!(pool.start_rethrow(), vec![first]);
+        let breakdown = pool.record(rolled(first, 3)).unwrap();
+        assert_eq!(breakdown.total, 11);
+        assert!(pool.can_hold(first));
+
+        // The held die keeps its face, and only the other one is thrown.
+        assert_eq!(pool.start_roll(1, Duration::ZERO, &[first]), vec![second]);
+        let breakdown = pool.record(rolled(second, 5)).unwrap();
+        assert_eq!(breakdown.total, 8);
+        assert_eq!(pool.thrown, vec![(DieKind::D6, rolled(second, 5))]);
+    }
+}
@@ -0,0 +1,541 @@
+//! Recording rolls to a file, and replaying them exactly, in the gameplay screen or headlessly.
+//!
+//! A recording holds everything needed to throw the dice again: the seed of the roll, where
+//! each die was resting, the impulses it was thrown with, and the fixed timestep physics was
+//! stepped with. Press [`SAVE_KEY`] after a roll to save it to `replays/`, and
+//! [`REPLAY_KEY`] to replay `replays/replay.ron`, or run the game with `--replay <file>` to
+//! replay a roll without a window.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use avian3d::prelude::*;
+#[cfg(not(test))]
+use bevy::log::LogPlugin;
+use bevy::{
+    gltf::GltfPlugin, input::common_conditions::input_just_pressed, prelude::*,
+    state::app::StatesPlugin, time::TimeUpdateStrategy,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{
+    Die, DieKind, apply_initial_forces,
+    hold::Held,
+    notation::{DiceExpr, DiceExprError},
+    physics_config::DicePhysicsConfig,
+    pool::{DicePool, DicePoolRolled, collect_pool_results},
+    roll_dice,
+    roll_params::{DiceRng, RollParams},
+};
+use crate::{asset_tracking, menus::Menu, screens::Screen};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_event::<DiceThrown>();
+    app.add_event::<ReplayRoll>();
+    app.init_resource::<RollRecorder>();
+
+    app.add_systems(
+        Update,
+        (
+            replay_rolls.before(roll_dice),
+            record_throws.after(roll_dice).after(replay_rolls),
+            finish_recordings.after(collect_pool_results),
+            save_last_recording
+                .run_if(in_state(Screen::Gameplay).and(input_just_pressed(SAVE_KEY))),
+            load_replay.run_if(in_state(Screen::Gameplay).and(input_just_pressed(REPLAY_KEY))),
+        ),
+    );
+    app.add_systems(OnExit(Screen::Gameplay), abandon_replay);
+}
+
+const SAVE_KEY: KeyCode = KeyCode::F6;
+const REPLAY_KEY: KeyCode = KeyCode::F7;
+/// Where recordings are saved, relative to the working directory.
+const REPLAY_DIR: &str = "replays";
+/// The most fixed timesteps a headless replay may take to settle.
+const MAX_HEADLESS_STEPS: u32 = 64 * 60;
+
+/// Event published when the dice of a roll are thrown, with the impulses of each die.
+#[derive(Event, Debug, Clone)]
+pub struct DiceThrown {
+    pub seed: u64,
+    pub dice: Vec<(Entity, RollParams)>,
+}
+
+/// Event that throws the dice again exactly as in the given recording.
+#[derive(Event, Debug, Clone)]
+pub struct ReplayRoll(pub RollRecording);
+
+/// Everything needed to replay a roll of the dice pool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RollRecording {
+    /// The dice expression of the pool, which has to match when replaying.
+    pub expression: String,
+    pub seed: u64,
+    /// The fixed timestep physics was stepped with.
+    pub timestep: Duration,
+    /// Each die of the pool, in order, including the held dice that weren't thrown.
+    pub dice: Vec<RecordedDie>,
+    /// The faces the dice settled on, once the roll is over.
+    #[serde(default)]
+    pub faces: Vec<u32>,
+    #[serde(default)]
+    pub total: Option<i64>,
+}
+
+/// A die at the moment it was thrown.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RecordedDie {
+    pub kind: DieKind,
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+    pub impulse: [f32; 3],
+    pub angular_impulse: [f32; 3],
+    /// Whether the die was held, keeping its face instead of being thrown.
+    #[serde(default)]
+    pub held: bool,
+}
+
+impl RecordedDie {
+    fn new(kind: DieKind, transform: &Transform, params: &RollParams) -> Self {
+        Self {
+            kind,
+            translation: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+            impulse: params.impulse.to_array(),
+            angular_impulse: params.angular_impulse.to_array(),
+            held: false,
+        }
+    }
+
+    /// A held die, which keeps resting where it is instead of being thrown.
+    fn held(kind: DieKind, transform: &Transform) -> Self {
+        Self {
+            kind,
+            translation: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+            impulse: [0.0; 3],
+            angular_impulse: [0.0; 3],
+            held: true,
+        }
+    }
+
+    fn params(&self) -> RollParams {
+        RollParams {
+            impulse: Vec3::from_array(self.impulse),
+            angular_impulse: Vec3::from_array(self.angular_impulse),
+        }
+    }
+}
+
+impl RollRecording {
+    pub fn to_ron(&self) -> Result<String, ReplayError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn from_ron(ron: &str) -> Result<Self, ReplayError> {
+        Ok(ron::de::from_str(ron)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        Self::from_ron(&fs::read_to_string(path)?)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("Couldn't access the recording: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Couldn't write the recording: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("Couldn't read the recording: {0}")]
+    Deserialize(#[from] ron::error::SpannedError),
+    #[error("The recorded dice expression is invalid: {0}")]
+    Expression(#[from] DiceExprError),
+    #[error("The recording is for {recorded}, but the dice pool is {pool}")]
+    ExpressionMismatch { recorded: String, pool: String },
+    #[error("The recording has {recorded} dice, but the dice pool has {pool}")]
+    DiceMismatch { recorded: usize, pool: usize },
+    #[error("The dice are still rolling")]
+    StillRolling,
+    #[error("No roll has been recorded yet")]
+    NothingRecorded,
+    #[error("The dice didn't settle within {0} steps")]
+    Timeout(u32),
+}
+
+/// The latest roll, and the roll being replayed if any.
+#[derive(Resource, Debug, Default)]
+pub(super) struct RollRecorder {
+    pub(super) last: Option<RollRecording>,
+    replaying: Option<RollRecording>,
+    /// The fixed timestep of the game, to restore once the replay is over.
+    timestep: Option<Duration>,
+}
+
+impl RollRecorder {
+    /// Stops replaying, and returns the fixed timestep to go back to if the replay changed it.
+    fn finish_replay(&mut self) -> (Option<RollRecording>, Option<Duration>) {
+        (self.replaying.take(), self.timestep.take())
+    }
+}
+
+/// Records where the dice were when they were thrown, and where the held dice rested
+fn record_throws(
+    mut thrown: EventReader<DiceThrown>,
+    pool: Res<DicePool>,
+    fixed_time: Res<Time<Fixed>>,
+    dice: Query<(&DieKind, &Transform), With<Die>>,
+    mut recorder: ResMut<RollRecorder>,
+) {
+    for throw in thrown.read() {
+        recorder.last = Some(RollRecording {
+            expression: pool.expression().to_string(),
+            seed: throw.seed,
+            timestep: fixed_time.timestep(),
+            dice: pool
+                .entities()
+                .into_iter()
+                .filter_map(|entity| {
+                    let (kind, transform) = dice.get(entity).ok()?;
+                    let params = throw.dice.iter().find(|(thrown, _)| *thrown == entity);
+                    Some(match params {
+                        Some((_, params)) => RecordedDie::new(*kind, transform, params),
+                        None => RecordedDie::held(*kind, transform),
+                    })
+                })
+                .collect(),
+            faces: Vec::new(),
+            total: None,
+        });
+    }
+}
+
+/// Adds the outcome of the roll to its recording, and checks replays against theirs
+fn finish_recordings(
+    mut pool_rolled: EventReader<DicePoolRolled>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    mut recorder: ResMut<RollRecorder>,
+) {
+    for rolled in pool_rolled.read() {
+        let faces: Vec<u32> = rolled.dice.iter().map(|die| die.face).collect();
+        if let Some(last) = recorder
+            .last
+            .as_mut()
+            .filter(|last| last.seed == rolled.seed)
+        {
+            last.faces = faces.clone();
+            last.total = Some(rolled.total);
+        }
+
+        let (replay, timestep) = recorder.finish_replay();
+        if let Some(timestep) = timestep {
+            fixed_time.set_timestep(timestep);
+        }
+        if let Some(replay) = replay {
+            if replay.faces.is_empty() || replay.faces == faces {
+                info!("Replayed roll {}: {faces:?}", replay.seed);
+            } else {
+                warn!(
+                    "Replayed roll {} settled on {faces:?}, but was recorded as {:?}",
+                    replay.seed, replay.faces
+                );
+            }
+        }
+    }
+}
+
+/// Goes back to the game's fixed timestep if the gameplay screen is left mid-replay
+fn abandon_replay(mut fixed_time: ResMut<Time<Fixed>>, mut recorder: ResMut<RollRecorder>) {
+    if let (_, Some(timestep)) = recorder.finish_replay() {
+        fixed_time.set_timestep(timestep);
+    }
+}
+
+fn save_last_recording(recorder: Res<RollRecorder>) {
+    let result = recorder
+        .last
+        .as_ref()
+        .ok_or(ReplayError::NothingRecorded)
+        .and_then(|last| {
+            let path = PathBuf::from(REPLAY_DIR).join(format!("roll-{}.ron", last.seed));
+            last.save(&path).map(|()| path)
+        });
+    match result {
+        Ok(path) => info!("Saved the last roll to {}", path.display()),
+        Err(error) => error!("Couldn't save the last roll: {error}"),
+    }
+}
+
+fn load_replay(mut replays: EventWriter<ReplayRoll>) {
+    let path = PathBuf::from(REPLAY_DIR).join("replay.ron");
+    match RollRecording::load(&path) {
+        Ok(recording) => {
+            replays.write(ReplayRoll(recording));
+        }
+        Err(error) => error!("Couldn't load {}: {error}", path.display()),
+    }
+}
+
+/// Throws the dice of the pool exactly as recorded, and puts the held dice back in place
+fn replay_rolls(
+    mut replays: EventReader<ReplayRoll>,
+    mut commands: Commands,
+    time: Res<Time>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+    mut rng: ResMut<DiceRng>,
+    mut pool: ResMut<DicePool>,
+    config: Res<DicePhysicsConfig>,
+    mut recorder: ResMut<RollRecorder>,
+    mut thrown: EventWriter<DiceThrown>,
+    mut dice: Query<(
+        &mut Die,
+        &DieKind,
+        &mut Transform,
+        &mut LinearVelocity,
+        &mut AngularVelocity,
+        &mut ExternalForce,
+        &mut ExternalTorque,
+        &mut ExternalImpulse,
+        &mut ExternalAngularImpulse,
+    )>,
+) {
+    for ReplayRoll(recording) in replays.read() {
+        let pool_expression = pool.expression().to_string();
+        if recording.expression != pool_expression {
+            error!(
+                "Couldn't replay roll: {}",
+                ReplayError::ExpressionMismatch {
+                    recorded: recording.expression.clone(),
+                    pool: pool_expression,
+                }
+            );
+            continue;
+        }
+        if pool.is_rolling() {
+            error!("Couldn't replay roll: {}", ReplayError::StillRolling);
+            continue;
+        }
+        let entities = pool.entities();
+        if entities.len() != recording.dice.len() {
+            error!(
+                "Couldn't replay roll: {}",
+                ReplayError::DiceMismatch {
+                    recorded: recording.dice.len(),
+                    pool: entities.len(),
+                }
+            );
+            continue;
+        }
+
+        info!(
+            "Replaying {} (seed {})",
+            recording.expression, recording.seed
+        );
+        // Keep the game's own timestep if a replay is already running.
+        let timestep = recorder.timestep.unwrap_or(fixed_time.timestep());
+        recorder.timestep = Some(timestep);
+        fixed_time.set_timestep(recording.timestep);
+        rng.replay_roll(recording.seed);
+
+        let mut held = Vec::new();
+        let mut replayed = Vec::with_capacity(entities.len());
+        for (entity, recorded) in entities.into_iter().zip(&recording.dice) {
+            let Ok((
+                mut die,
+                kind,
+                mut transform,
+                mut velocity,
+                mut ang_velocity,
+                mut external_force,
+                mut external_torque,
+                mut external_impulse,
+                mut external_angular_impulse,
+            )) = dice.get_mut(entity)
+            else {
+                continue;
+            };
+            if *kind != recorded.kind {
+                warn!("Replaying a {:?} as a {kind:?}", recorded.kind);
+            }
+
+            transform.translation = Vec3::from_array(recorded.translation);
+            transform.rotation = Quat::from_array(recorded.rotation);
+            velocity.0 = Vec3::ZERO;
+            ang_velocity.0 = Vec3::ZERO;
+            if recorded.held {
+                pool.set_face(entity, kind.face_value(transform.rotation));
+                commands.entity(entity).insert(Held);
+                held.push(entity);
+                continue;
+            }
+            commands.entity(entity).remove::<Held>();
+
+            // Draw the impulses anyway, so that cocked rerolls and exploded dice draw the
+            // same numbers as in the recorded roll.
+            RollParams::generate(rng.rng(), &config.throw);
+            let params = recorded.params();
+            die.start_throw();
+            apply_initial_forces(
+                &params,
+                &mut external_force,
+                &mut external_torque,
+                &mut external_impulse,
+                &mut external_angular_impulse,
+            );
+            replayed.push((entity, params));
+        }
+        pool.start_roll(recording.seed, time.elapsed(), &held);
+
+        thrown.write(DiceThrown {
+            seed: recording.seed,
+            dice: replayed,
+        });
+        recorder.replaying = Some(recording.clone());
+    }
+}
+
+/// Builds an app with the dice roller, physics and the gameplay screen, but no window or
+/// renderer. Every update steps physics by exactly one fixed timestep.
+pub(super) fn headless_app(seed: u64, pool: DicePool, timestep: Duration) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        AssetPlugin::default(),
+        // The skins measure the model of the d6 to fit its markings.
+        GltfPlugin::default(),
+        StatesPlugin,
+        PhysicsPlugins::default(),
+        asset_tracking::plugin,
+    ));
+    // Replays from the command line report through the log, but tests keep quiet.
+    #[cfg(not(test))]
+    app.add_plugins(LogPlugin::default());
+    app.init_asset::<Mesh>();
+    app.init_asset::<StandardMaterial>();
+    app.init_asset::<Image>();
+    app.init_asset::<Scene>();
+    app.init_asset::<AudioSource>();
+    app.init_resource::<ButtonInput<MouseButton>>();
+    app.init_resource::<ButtonInput<KeyCode>>();
+    app.insert_resource(Time::<Fixed>::from_duration(timestep));
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+    app.init_state::<Screen>();
+    app.init_state::<Menu>();
+
+    app.add_plugins(super::plugin);
+    app.insert_resource(DiceRng::new(seed));
+    app.insert_resource(pool);
+    // Done by `App::run` otherwise, which the headless app never calls.
+    app.finish();
+    app.cleanup();
+
+    app.world_mut()
+        .resource_mut::<NextState<Screen>>()
+        .set(Screen::Gameplay);
+    app.update();
+    app
+}
+
+/// The dice faces and total of a replayed roll.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayOutcome {
+    pub faces: Vec<u32>,
+    pub total: i64,
+}
+
+/// Replays a recorded roll without a window.
+pub(super) fn replay_headless(recording: &RollRecording) -> Result<ReplayOutcome, ReplayError> {
+    let expression: DiceExpr = recording.expression.parse()?;
+    let mut app = headless_app(
+        recording.seed,
+        DicePool::new(expression),
+        recording.timestep,
+    );
+    // Build the tray before throwing the dice in it.
+    app.update();
+
+    app.world_mut().send_event(ReplayRoll(recording.clone()));
+    for _ in 0..MAX_HEADLESS_STEPS {
+        app.update();
+        let events = app.world().resource::<Events<DicePoolRolled>>();
+        if let Some(rolled) = events.iter_current_update_events().next() {
+            return Ok(ReplayOutcome {
+                faces: rolled.dice.iter().map(|die| die.face).collect(),
+                total: rolled.total,
+            });
+        }
+    }
+    Err(ReplayError::Timeout(MAX_HEADLESS_STEPS))
+}
+
+/// Replays the recording at `path` without a window, and reports whether the dice settled
+/// as recorded.
+pub fn replay_roll_file(path: &Path) -> AppExit {
+    let result = RollRecording::load(path).and_then(|recording| {
+        let outcome = replay_headless(&recording)?;
+        Ok((recording, outcome))
+    });
+    match result {
+        Ok((recording, outcome)) => {
+            info!(
+                "Replayed {} (seed {}): {:?} = {}",
+                recording.expression, recording.seed, outcome.faces, outcome.total
+            );
+            if recording.faces.is_empty() || recording.faces == outcome.faces {
+                AppExit::Success
+            } else {
+                error!("The roll was recorded as {:?}", recording.faces);
+                AppExit::error()
+            }
+        }
+        Err(error) => {
+            // The recording may not have loaded far enough for the headless app to install
+            // its logger.
+            eprintln!("Couldn't replay {}: {error}", path.display());
+            AppExit::error()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recordings_round_trip_through_ron() {
+        let recording = RollRecording {
+            expression: "2d6".to_string(),
+            seed: 99,
+            timestep: Duration::from_micros(15_625),
+            dice: vec![RecordedDie {
+                kind: DieKind::D6,
+                translation: [0.5, 0.4, -0.25],
+                rotation: Quat::from_rotation_y(0.3).to_array(),
+                impulse: [6.0, 1.0, -2.0],
+                angular_impulse: [3.0, -4.0, 5.0],
+                held: false,
+            }],
+            faces: vec![4],
+            total: Some(4),
+        };
+        let ron = recording.to_ron().unwrap();
+        assert_eq!(RollRecording::from_ron(&ron).unwrap(), recording);
+    }
+}
@@ -0,0 +1,148 @@
+//! Randomized, seedable impulses for rolling dice.
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::{Rng, RngCore, SeedableRng, rngs::StdRng};
+
+use super::physics_config::ThrowConfig;
+
+/// The random number generator used for every dice roll.
+///
+/// It is seeded from a `u64` so that a session can be replayed exactly,
+/// for tests and bug reports. Each roll draws its own seed from the session,
+/// so that a single roll can be replayed too.
+#[derive(Resource, Debug, Clone)]
+pub struct DiceRng {
+    seed: u64,
+    session: StdRng,
+    roll_seed: u64,
+    roll: StdRng,
+}
+
+impl DiceRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            session: StdRng::seed_from_u64(seed),
+            roll_seed: seed,
+            roll: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// The seed this generator was created from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Draws the seed of a new roll from the session. Returns the roll's seed.
+    pub fn start_roll(&mut self) -> u64 {
+        let seed = self.session.next_u64();
+        self.replay_roll(seed);
+        seed
+    }
+
+    /// Restarts the current roll from the given seed.
+    pub fn replay_roll(&mut self, seed: u64) {
+        self.roll_seed = seed;
+        self.roll = StdRng::seed_from_u64(seed);
+    }
+
+    /// The seed of the current roll.
+    pub fn roll_seed(&self) -> u64 {
+        self.roll_seed
+    }
+
+    /// The generator of the current roll.
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.roll
+    }
+}
+
+impl Default for DiceRng {
+    fn default() -> Self {
+        Self::new(rand::random())
+    }
+}
+
+/// The impulses that throw a single die.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RollParams {
+    /// Linear impulse applied to the die's center of mass.
+    pub impulse: Vec3,
+    /// Angular impulse that sets the die spinning.
+    pub angular_impulse: Vec3,
+}
+
+impl RollParams {
+    /// Draws a throw direction, strength and spin within the ranges of `config` from the
+    /// given generator.
+    pub fn generate(rng: &mut impl Rng, config: &ThrowConfig) -> Self {
+        let angle = rng.gen_range(0.0..TAU);
+        let lift = rng.gen_range(config.impulse_lift.clone());
+        let direction = Vec3::new(angle.cos(), lift, angle.sin()).normalize();
+        let strength = rng.gen_range(config.impulse_strength.clone());
+
+        let axis = Vec3::new(
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+        )
+        .try_normalize()
+        .unwrap_or(Vec3::X);
+        let spin = rng.gen_range(config.spin_strength.clone());
+
+        Self {
+            impulse: direction * strength,
+            angular_impulse: axis * spin,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_replays_same_rolls() {
+        let mut a = DiceRng::new(42);
+        let mut b = DiceRng::new(42);
+        let config = ThrowConfig::default();
+        for _ in 0..8 {
+            assert_eq!(
+                RollParams::generate(a.rng(), &config),
+                RollParams::generate(b.rng(), &config)
+            );
+        }
+    }
+
+    #[test]
+    fn each_roll_draws_its_own_seed() {
+        let mut a = DiceRng::new(42);
+        let mut b = DiceRng::new(42);
+        let first = a.start_roll();
+        assert_eq!(first, b.start_roll());
+        assert_ne!(first, a.start_roll());
+
+        let config = ThrowConfig::default();
+        let params = RollParams::generate(b.rng(), &config);
+        b.replay_roll(first);
+        assert_eq!(RollParams::generate(b.rng(), &config), params);
+    }
+
+    #[test]
+    fn generated_params_stay_in_range() {
+        let mut rng = DiceRng::new(7);
+        let config = ThrowConfig::default();
+        for _ in 0..64 {
+            let params = RollParams::generate(rng.rng(), &config);
+            let strength = params.impulse.length();
+            let range = &config.impulse_strength;
+            assert!(range.start <= strength && strength <= range.end);
+            assert!(params.impulse.y > 0.0);
+            let spin = params.angular_impulse.length();
+            let range = &config.spin_strength;
+            assert!(range.start <= spin && spin <= range.end);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Detects when a thrown die has come to rest.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+/// How still a die has to be, and for how long, before its roll is read.
+#[derive(Resource, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Resource)]
+pub struct SettleConfig {
+    /// Linear speed, in m/s, below which a die counts as still.
+    pub linear_threshold: f32,
+    /// Angular speed, in rad/s, below which a die counts as still.
+    pub angular_threshold: f32,
+    /// How long a die has to stay still before it is settled.
+    pub window: Duration,
+    /// How long after a throw the die is ignored, so that the throw's impulse has time to apply.
+    pub grace: Duration,
+}
+
+impl Default for SettleConfig {
+    fn default() -> Self {
+        Self {
+            linear_threshold: 0.1,
+            angular_threshold: 0.2,
+            window: Duration::from_millis(300),
+            grace: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Whether a die is still moving, slowing down, or at rest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettleStatus {
+    Moving,
+    /// Below the thresholds, but not yet for the whole window.
+    Settling,
+    Settled,
+}
+
+/// Tracks how long a die has been thrown and how long it has stayed still.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SettleDetector {
+    since_throw: Duration,
+    still_for: Duration,
+}
+
+impl SettleDetector {
+    /// Starts over, e.g. when the die is thrown again.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Advances the detector by `delta` given the die's current motion.
+    ///
+    /// A sleeping body is settled right away, since the physics engine only puts a body
+    /// to sleep once it has been at rest for a while.
+    pub fn update(
+        &mut self,
+        config: &SettleConfig,
+        delta: Duration,
+        linear_speed: f32,
+        angular_speed: f32,
+        sleeping: bool,
+    ) -> SettleStatus {
+        self.since_throw += delta;
+        if self.since_throw < config.grace {
+            return SettleStatus::Moving;
+        }
+        if sleeping {
+            self.still_for = config.window;
+            return SettleStatus::Settled;
+        }
+
+        if linear_speed < config.linear_threshold && angular_speed < config.angular_threshold {
+            self.still_for += delta;
+        } else {
+            self.still_for = Duration::ZERO;
+            return SettleStatus::Moving;
+        }
+
+        if self.still_for >= config.window {
+            SettleStatus::Settled
+        } else {
+            SettleStatus::Settling
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(50);
+
+    fn run(detector: &mut SettleDetector, steps: u32, speed: f32) -> SettleStatus {
+        let config = SettleConfig::default();
+        let mut status = SettleStatus::Moving;
+        for _ in 0..steps {
+            status = detector.update(&config, STEP, speed, speed, false);
+        }
+        status
+    }
+
+    #[test]
+    fn needs_a_sustained_window_to_settle() {
+        let mut detector = SettleDetector::default();
+        assert_eq!(run(&mut detector, 1, 0.0), SettleStatus::Moving);
+        assert_eq!(run(&mut detector, 5, 0.0), SettleStatus::Settling);
+        assert_eq!(run(&mut detector, 1, 0.0), SettleStatus::Settled);
+    }
+
+    #[test]
+    fn moving_again_restarts_the_window() {
+        let mut detector = SettleDetector::default();
+        assert_eq!(run(&mut detector, 6, 0.0), SettleStatus::Settling);
+        assert_eq!(run(&mut detector, 1, 1.0), SettleStatus::Moving);
+        assert_eq!(run(&mut detector, 5, 0.0), SettleStatus::Settling);
+        assert_eq!(run(&mut detector, 1, 0.0), SettleStatus::Settled);
+    }
+
+    #[test]
+    fn sleeping_settles_after_the_grace_period() {
+        let config = SettleConfig::default();
+        let mut detector = SettleDetector::default();
+        assert_eq!(
+            detector.update(&config, STEP, 0.0, 0.0, true),
+            SettleStatus::Moving
+        );
+        assert_eq!(
+            detector.update(&config, STEP, 0.0, 0.0, true),
+            SettleStatus::Settled
+        );
+    }
+}
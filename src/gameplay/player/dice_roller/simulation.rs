@@ -0,0 +1,208 @@
+//! Headless physics simulation of dice rolls, without a window or renderer.
+//! Rolls are stepped on a fixed timestep from a seeded [`DiceRng`](super::DiceRng), so they replay exactly.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use super::{
+    Die, DieState, QUICK_ROLL_KEY,
+    hold::Held,
+    pool::{DicePool, DicePoolRolled},
+    replay::{RollRecorder, RollRecording, headless_app, replay_headless},
+    tray::DiceTray,
+};
+use crate::screens::Screen;
+
+/// Duration of a single simulation step, matching the default fixed timestep.
+const TIMESTEP: Duration = Duration::from_micros(15_625);
+/// The most steps a roll may take to settle before the simulation gives up.
+const MAX_STEPS: u32 = 64 * 20;
+/// Half the inner width of the default dice tray.
+const BOX_HALF_SIZE: f32 = 1.5;
+
+/// The outcome of a simulated roll.
+#[derive(Debug, Clone, PartialEq)]
+struct SimulatedRoll {
+    faces: Vec<u32>,
+    total: i64,
+    /// Number of steps between the throw and every die settling.
+    settle_steps: u32,
+    /// Whether every die stayed inside the tray on every step.
+    stayed_in_box: bool,
+}
+
+/// Lets the dice fall onto the tray, throws them, and steps the simulation until they settle.
+fn simulate_roll(seed: u64) -> SimulatedRoll {
+    simulate_recorded_roll(seed).0
+}
+
+/// Like [`simulate_roll`], also returning the recording of the roll.
+fn simulate_recorded_roll(seed: u64) -> (SimulatedRoll, RollRecording) {
+    let mut app = headless_app(seed, DicePool::default(), TIMESTEP);
+
+    // Let the freshly spawned dice come to rest first.
+    for _ in 0..64 {
+        app.update();
+    }
+    throw_and_settle(&mut app, seed)
+}
+
+/// Throws the dice that aren't held, and steps the simulation until they settle.
+fn throw_and_settle(app: &mut App, seed: u64) -> (SimulatedRoll, RollRecording) {
+    app.world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(QUICK_ROLL_KEY);
+    app.update();
+    app.world_mut()
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .reset_all();
+
+    let mut stayed_in_box = true;
+    for step in 1..=MAX_STEPS {
+        app.update();
+
+        let mut dice = app.world_mut().query_filtered::<&Transform, With<Die>>();
+        stayed_in_box &= dice.iter(app.world()).all(|transform| {
+            let position = transform.translation;
+            position.x.abs() < BOX_HALF_SIZE && position.z.abs() < BOX_HALF_SIZE && position.y > 0.0
+        });
+
+        let events = app.world().resource::<Events<DicePoolRolled>>();
+        if let Some(rolled) = events.iter_current_update_events().next() {
+            let roll = SimulatedRoll {
+                faces: rolled.dice.iter().map(|die| die.face).collect(),
+                total: rolled.total,
+                settle_steps: step,
+                stayed_in_box,
+            };
+            let recording = app
+                .world()
+                .resource::<RollRecorder>()
+                .last
+                .clone()
+                .expect("the roll should have been recorded");
+            return (roll, recording);
+        }
+    }
+
+    let mut dice = app.world_mut().query::<(&Die, &Transform)>();
+    let states: Vec<_> = dice
+        .iter(app.world())
+        .map(|(die, transform)| {
+            (
+                matches!(die.state, DieState::Rolling),
+                transform.translation,
+            )
+        })
+        .collect();
+    panic!("Seed {seed} didn't settle after {MAX_STEPS} steps: {states:?}");
+}
+
+#[test]
+fn seeded_roll_replays_exactly() {
+    let first = simulate_roll(1234);
+    let second = simulate_roll(1234);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn seeded_rolls_settle_flat_inside_the_box() {
+    let mut faces = Vec::new();
+    for seed in 0..8 {
+        let (roll, recording) = simulate_recorded_roll(seed);
+        assert_eq!(roll.faces.len(), 2, "seed {seed}: {roll:?}");
+        assert!(
+            roll.faces.iter().all(|face| (1..=6).contains(face)),
+            "seed {seed}: {roll:?}"
+        );
+        assert_eq!(roll.total, roll.faces.iter().sum::<u32>() as i64);
+        assert!(
+            roll.settle_steps < MAX_STEPS,
+            "seed {seed} took too long to settle"
+        );
+        assert!(roll.stayed_in_box, "seed {seed} left the box: {roll:?}");
+        // A fresh app thrown from the recording has to settle on the same faces.
+        assert_eq!(
+            replay_headless(&recording).unwrap().faces,
+            roll.faces,
+            "seed {seed} settled on other faces when replayed"
+        );
+        faces.push(roll.faces);
+    }
+    // Different seeds have to throw the dice differently.
+    assert!(
+        faces.windows(2).any(|pair| pair[0] != pair[1]),
+        "every seed settled on {:?}",
+        faces[0]
+    );
+}
+
+#[test]
+fn recorded_roll_replays_headlessly() {
+    let (roll, recording) = simulate_recorded_roll(42);
+    assert_eq!(recording.faces, roll.faces);
+    assert_eq!(recording.dice.len(), 2);
+
+    let recording = RollRecording::from_ron(&recording.to_ron().unwrap()).unwrap();
+    let first = replay_headless(&recording).unwrap();
+    let second = replay_headless(&recording).unwrap();
+    assert_eq!(first, second);
+    assert_eq!(first.faces.len(), 2);
+}
+
+#[test]
+fn reroll_with_a_held_die_replays_headlessly() {
+    let mut app = headless_app(5, DicePool::default(), TIMESTEP);
+    for _ in 0..64 {
+        app.update();
+    }
+    let (first, _) = throw_and_settle(&mut app, 5);
+    let held = app.world().resource::<DicePool>().entities()[0];
+    app.world_mut().entity_mut(held).insert(Held);
+
+    let (reroll, recording) = throw_and_settle(&mut app, 5);
+    assert_eq!(reroll.faces[0], first.faces[0]);
+    let held: Vec<bool> = recording.dice.iter().map(|die| die.held).collect();
+    assert_eq!(held, [true, false]);
+
+    let replayed = replay_headless(&recording).unwrap();
+    assert_eq!(replayed.faces[0], first.faces[0]);
+    assert_eq!(replayed, replay_headless(&recording).unwrap());
+}
+
+/// The number of dice, of trays and of entities overall.
+fn entity_counts(app: &mut App) -> (usize, usize, u32) {
+    let world = app.world_mut();
+    let dice = world.query_filtered::<(), With<Die>>().iter(world).count();
+    let trays = world
+        .query_filtered::<(), With<DiceTray>>()
+        .iter(world)
+        .count();
+    (dice, trays, world.entities().len())
+}
+
+fn set_screen(app: &mut App, screen: Screen) {
+    app.world_mut()
+        .resource_mut::<NextState<Screen>>()
+        .set(screen);
+    app.update();
+}
+
+#[test]
+fn reentering_gameplay_keeps_entity_counts_stable() {
+    let mut app = headless_app(7, DicePool::default(), TIMESTEP);
+    let in_gameplay = entity_counts(&mut app);
+    assert_eq!((in_gameplay.0, in_gameplay.1), (2, 1));
+
+    set_screen(&mut app, Screen::Title);
+    let outside = entity_counts(&mut app);
+    assert_eq!((outside.0, outside.1), (0, 0));
+
+    for _ in 0..3 {
+        set_screen(&mut app, Screen::Gameplay);
+        assert_eq!(entity_counts(&mut app), in_gameplay);
+        set_screen(&mut app, Screen::Title);
+        assert_eq!(entity_counts(&mut app), outside);
+    }
+}
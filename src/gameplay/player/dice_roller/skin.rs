@@ -0,0 +1,699 @@
+//! Skins that change how dice look, loaded from `assets/skins/*.skin.ron`.
+//!
+//! A skin colors the body of a die and the markings of its faces, either textures, the
+//! pips of a d6, or the numbers of other dice. Each seat at the table has a skin, and every die is drawn with the skin
+//! of the seat rolling it unless it has a skin of its own, so that the dice of a pool, or
+//! of different players, are easy to tell apart.
+
+use std::f32::consts::SQRT_2;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, RenderAssetUsages, io::Reader},
+    gltf::{GltfMesh, GltfNode},
+    platform::collections::HashMap,
+    prelude::*,
+    render::mesh::{Indices, PrimitiveTopology},
+    scene::SceneInstanceReady,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::{
+    Die,
+    kind::{DieKind, FaceDecal},
+    pool::DicePool,
+};
+use crate::{asset_tracking::LoadResource, gameplay::models::bounds::measure_model};
+
+pub(super) fn plugin(app: &mut App) {
+    app.init_asset::<DieSkin>();
+    app.init_asset_loader::<DieSkinLoader>();
+    app.register_type::<DieSkinChoice>();
+    app.init_resource::<DieSkinChoice>();
+    app.init_resource::<DieSkinMaterials>();
+    app.init_resource::<DieModelBounds>();
+    app.load_resource::<DieSkins>();
+
+    app.add_observer(paint_die_model);
+    app.add_systems(
+        Update,
+        (
+            measure_die_model,
+            apply_die_skins.run_if(resource_exists::<DieSkins>),
+        )
+            .chain(),
+    );
+}
+
+/// The model of a d6.
+pub(super) const DIE_MODEL: &str = "models/dice.glb";
+
+/// How far above its face a marking floats, so that it doesn't flicker through it.
+const MARKING_OFFSET: f32 = 0.002;
+/// Radius of a pip, as a fraction of the radius of its face.
+const PIP_RADIUS: f32 = 0.16;
+/// Distance between rows of pips, as a fraction of the radius of their face.
+const PIP_SPACING: f32 = 0.5;
+/// Height of the digits of a number, as a fraction of the radius of its face.
+const NUMBER_SIZE: f32 = 0.9;
+/// Width of a digit, as a fraction of its height.
+const DIGIT_WIDTH: f32 = 0.5;
+/// Thickness of the strokes of a digit, as a fraction of its height.
+const DIGIT_STROKE: f32 = 0.12;
+/// Space between two digits, as a fraction of their height.
+const DIGIT_SPACING: f32 = 0.2;
+
+/// The look of a die.
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct DieSkin {
+    pub name: String,
+    pub body_color: Color,
+    pub metallic: f32,
+    pub perceptual_roughness: f32,
+    /// Whether the die ignores lighting.
+    pub unlit: bool,
+    /// The color of the pips of a d6 and the numbers of other dice without face textures,
+    /// which the face textures are tinted with otherwise.
+    pub pip_color: Color,
+    /// The texture marking each face, indexed by face value minus one.
+    pub face_textures: Vec<Option<Handle<Image>>>,
+}
+
+impl DieSkin {
+    /// The texture marking the face with the given value, if there is one.
+    pub fn face_texture(&self, value: u32) -> Option<&Handle<Image>> {
+        self.face_textures
+            .get(value.checked_sub(1)? as usize)?
+            .as_ref()
+    }
+
+    fn body_material(&self) -> StandardMaterial {
+        StandardMaterial {
+            base_color: self.body_color,
+            metallic: self.metallic,
+            perceptual_roughness: self.perceptual_roughness,
+            unlit: self.unlit,
+            ..default()
+        }
+    }
+
+    fn pip_material(&self) -> StandardMaterial {
+        StandardMaterial {
+            base_color: self.pip_color,
+            unlit: self.unlit,
+            ..default()
+        }
+    }
+
+    fn face_material(&self, texture: Handle<Image>) -> StandardMaterial {
+        StandardMaterial {
+            base_color: self.pip_color,
+            base_color_texture: Some(texture),
+            alpha_mode: AlphaMode::Mask(0.5),
+            unlit: self.unlit,
+            ..default()
+        }
+    }
+}
+
+/// A skin as written in a `.skin.ron` file, with colors in sRGB.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+struct DieSkinFile {
+    name: String,
+    body_color: [f32; 3],
+    metallic: f32,
+    perceptual_roughness: f32,
+    unlit: bool,
+    pip_color: [f32; 3],
+    /// Asset paths of the face textures, indexed by face value minus one.
+    face_textures: Vec<Option<String>>,
+}
+
+impl Default for DieSkinFile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            body_color: [1.0; 3],
+            metallic: 0.0,
+            perceptual_roughness: 0.5,
+            unlit: false,
+            pip_color: [0.0; 3],
+            face_textures: Vec::new(),
+        }
+    }
+}
+
+/// Which skin each die is drawn with.
+#[derive(Resource, Reflect, Debug, Clone, PartialEq, Eq)]
+#[reflect(Resource)]
+pub struct DieSkinChoice {
+    /// The skin of the dice of each seat, as an index into the loaded skins.
+    pub seats: Vec<usize>,
+    /// The seat rolling the dice.
+    pub seat: usize,
+    /// Skins of individual dice by their position in the pool, overriding the seat's.
+    pub dice: Vec<Option<usize>>,
+}
+
+impl Default for DieSkinChoice {
+    fn default() -> Self {
+        Self {
+            // A different skin for each of the shipped skins.
+            seats: vec![0, 1, 2, 3],
+            seat: 0,
+            dice: Vec::new(),
+        }
+    }
+}
+
+impl DieSkinChoice {
+    /// The skin of the die at the given position in the pool.
+    pub fn skin_of(&self, die: usize) -> usize {
+        self.dice
+            .get(die)
+            .copied()
+            .flatten()
+            .or_else(|| self.seats.get(self.seat).copied())
+            .unwrap_or_default()
+    }
+}
+
+/// Every skin shipped with the game, in the order [`DieSkinChoice`] refers to them.
+#[derive(Resource, Asset, Clone, Reflect)]
+#[reflect(Resource)]
+struct DieSkins {
+    #[dependency]
+    skins: Vec<Handle<DieSkin>>,
+}
+
+impl DieSkins {
+    fn get(&self, index: usize) -> Option<&Handle<DieSkin>> {
+        self.skins.get(index % self.skins.len().max(1))
+    }
+}
+
+impl FromWorld for DieSkins {
+    fn from_world(world: &mut World) -> Self {
+        let assets = world.resource::<AssetServer>();
+        Self {
+            skins: ["ivory", "ruby", "jade", "onyx"]
+                .into_iter()
+                .map(|name| assets.load(format!("skins/{name}.skin.ron")))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+enum DieSkinError {
+    #[error("Couldn't read the die skin: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Couldn't parse the die skin: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+#[derive(Default)]
+struct DieSkinLoader;
+
+impl AssetLoader for DieSkinLoader {
+    type Asset = DieSkin;
+    type Settings = ();
+    type Error = DieSkinError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: DieSkinFile = ron::de::from_bytes(&bytes)?;
+        Ok(DieSkin {
+            name: file.name,
+            body_color: Color::srgb_from_array(file.body_color),
+            metallic: file.metallic,
+            perceptual_roughness: file.perceptual_roughness,
+            unlit: file.unlit,
+            pip_color: Color::srgb_from_array(file.pip_color),
+            face_textures: file
+                .face_textures
+                .into_iter()
+                .map(|path| path.map(|path| load_context.load(path)))
+                .collect(),
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["skin.ron"]
+    }
+}
+
+/// The materials and meshes every die drawn with a skin shares.
+#[derive(Resource, Debug, Default)]
+struct DieSkinMaterials {
+    bodies: HashMap<AssetId<DieSkin>, Handle<StandardMaterial>>,
+    faces: HashMap<(AssetId<DieSkin>, u32), Handle<StandardMaterial>>,
+    pips: HashMap<AssetId<DieSkin>, Handle<StandardMaterial>>,
+    /// The mesh of each face marking, by the kind of die and the value of the face.
+    markings: HashMap<(DieKind, u32), Handle<Mesh>>,
+    pip_meshes: HashMap<DieKind, Handle<Mesh>>,
+    /// The mesh of each number, one unit high, by its value.
+    numbers: HashMap<u32, Handle<Mesh>>,
+}
+
+/// The box around the model of a d6, which its face markings are fitted to, once measured.
+#[derive(Resource, Debug)]
+struct DieModelBounds {
+    model: Handle<Gltf>,
+    bounds: Option<(Vec3, Vec3)>,
+}
+
+impl FromWorld for DieModelBounds {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            model: world.resource::<AssetServer>().load(DIE_MODEL),
+            bounds: None,
+        }
+    }
+}
+
+fn measure_die_model(
+    mut model: ResMut<DieModelBounds>,
+    gltf: Res<Assets<Gltf>>,
+    nodes: Res<Assets<GltfNode>>,
+    gltf_meshes: Res<Assets<GltfMesh>>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    if model.bounds.is_some() {
+        return;
+    }
+    let Some(gltf) = gltf.get(&model.model) else {
+        return;
+    };
+    model.bounds = measure_model(gltf, &nodes, &gltf_meshes, &meshes);
+}
+
+/// Moves and scales the marking of a face onto the box of a model of the die.
+fn fit_to_model(decal: FaceDecal, (min, max): (Vec3, Vec3)) -> FaceDecal {
+    let half_size = (max - min) / 2.0;
+    let scale = half_size.dot(decal.normal.abs()) / decal.center.dot(decal.normal);
+    FaceDecal {
+        center: (min + max) / 2.0 + decal.center * scale,
+        radius: decal.radius * scale,
+        ..decal
+    }
+}
+
+/// The model of a die, spawned as a child of the die so that its meshes can be skinned.
+#[derive(Component, Debug)]
+pub(super) struct DieModel;
+
+/// The marking of a single face of a die.
+#[derive(Component, Debug)]
+struct FaceMarking;
+
+/// Gives each die the skin chosen for it, whenever the choice, the skins or the dice change
+fn apply_die_skins(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<DieSkin>>,
+    skins: Res<DieSkins>,
+    choice: Res<DieSkinChoice>,
+    model_bounds: Res<DieModelBounds>,
+    skin_assets: Res<Assets<DieSkin>>,
+    pool: Res<DicePool>,
+    mut cache: ResMut<DieSkinMaterials>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    new_dice: Query<(), Added<Die>>,
+    dice: Query<(&DieKind, Option<&Children>)>,
+    models: Query<(), With<DieModel>>,
+    markings: Query<(), With<FaceMarking>>,
+    children: Query<&Children>,
+    model_meshes: Query<(), With<Mesh3d>>,
+) {
+    let reloaded = events.read().count() > 0;
+    if !reloaded
+        && !skins.is_added()
+        && !choice.is_changed()
+        && !model_bounds.is_changed()
+        && new_dice.is_empty()
+    {
+        return;
+    }
+    if reloaded {
+        cache.bodies.clear();
+        cache.faces.clear();
+        cache.pips.clear();
+    }
+    if model_bounds.is_changed() {
+        cache.markings.clear();
+        cache.pip_meshes.clear();
+    }
+
+    for (index, entity) in pool.entities().into_iter().enumerate() {
+        let Some((id, skin)) = skins
+            .get(choice.skin_of(index))
+            .and_then(|handle| Some((handle.id(), skin_assets.get(handle)?)))
+        else {
+            continue;
+        };
+        let Ok((kind, die_children)) = dice.get(entity) else {
+            continue;
+        };
+
+        let body = cache
+            .bodies
+            .entry(id)
+            .or_insert_with(|| materials.add(skin.body_material()))
+            .clone();
+        commands.entity(entity).insert(MeshMaterial3d(body.clone()));
+        for child in die_children.into_iter().flatten().copied() {
+            if markings.contains(child) {
+                commands.entity(child).despawn();
+            } else if models.contains(child) {
+                for descendant in children.iter_descendants(child) {
+                    if model_meshes.contains(descendant) {
+                        commands
+                            .entity(descendant)
+                            .insert(MeshMaterial3d(body.clone()));
+                    }
+                }
+            }
+        }
+
+        // The d6 is drawn with its model rather than the shape of its collider.
+        let decals: Vec<FaceDecal> = match (*kind, model_bounds.bounds) {
+            (DieKind::D6, Some(bounds)) => kind
+                .face_decals()
+                .into_iter()
+                .map(|decal| fit_to_model(decal, bounds))
+                .collect(),
+            (DieKind::D6, None) => Vec::new(),
+            _ => kind.face_decals(),
+        };
+        commands.entity(entity).with_children(|parent| {
+            for decal in decals {
+                let transform =
+                    Transform::from_translation(decal.center + decal.normal * MARKING_OFFSET)
+                        .with_rotation(Quat::from_rotation_arc(Vec3::Z, decal.normal));
+                if let Some(texture) = skin.face_texture(decal.value) {
+                    let mesh = cache
+                        .markings
+                        .entry((*kind, decal.value))
+                        .or_insert_with(|| {
+                            // The largest square that fits on the face.
+                            let size = decal.radius * SQRT_2;
+                            meshes.add(Rectangle::new(size, size))
+                        })
+                        .clone();
+                    let material = cache
+                        .faces
+                        .entry((id, decal.value))
+                        .or_insert_with(|| materials.add(skin.face_material(texture.clone())))
+                        .clone();
+                    parent.spawn((
+                        Name::new(format!("Face Marking {}", decal.value)),
+                        FaceMarking,
+                        transform,
+                        Mesh3d(mesh),
+                        MeshMaterial3d(material),
+                    ));
+                } else if *kind == DieKind::D6 {
+                    let mesh = cache
+                        .pip_meshes
+                        .entry(*kind)
+                        .or_insert_with(|| meshes.add(Circle::new(decal.radius * PIP_RADIUS)))
+                        .clone();
+                    let material = cache
+                        .pips
+                        .entry(id)
+                        .or_insert_with(|| materials.add(skin.pip_material()))
+                        .clone();
+                    parent
+                        .spawn((
+                            Name::new(format!("Face Marking {}", decal.value)),
+                            FaceMarking,
+                            transform,
+                            Visibility::default(),
+                        ))
+                        .with_children(|face| {
+                            for &pip in pip_layout(decal.value) {
+                                face.spawn((
+                                    Name::new("Pip"),
+                                    Transform::from_translation(
+                                        (pip * decal.radius * PIP_SPACING).extend(0.0),
+                                    ),
+                                    Mesh3d(mesh.clone()),
+                                    MeshMaterial3d(material.clone()),
+                                ));
+                            }
+                        });
+                } else {
+                    let mesh = cache
+                        .numbers
+                        .entry(decal.value)
+                        .or_insert_with(|| meshes.add(number_mesh(decal.value)))
+                        .clone();
+                    let material = cache
+                        .pips
+                        .entry(id)
+                        .or_insert_with(|| materials.add(skin.pip_material()))
+                        .clone();
+                    parent.spawn((
+                        Name::new(format!("Face Marking {}", decal.value)),
+                        FaceMarking,
+                        transform.with_scale(Vec3::splat(decal.radius * NUMBER_SIZE)),
+                        Mesh3d(mesh),
+                        MeshMaterial3d(material),
+                    ));
+                }
+            }
+        });
+    }
+}
+
+/// Where the pips of a d6 face go, on a grid from -1 to 1 across the face.
+fn pip_layout(value: u32) -> &'static [Vec2] {
+    const CENTER: Vec2 = Vec2::ZERO;
+    const LOW: Vec2 = Vec2::new(-1.0, -1.0);
+    const HIGH: Vec2 = Vec2::new(1.0, 1.0);
+    const LEFT_HIGH: Vec2 = Vec2::new(-1.0, 1.0);
+    const RIGHT_LOW: Vec2 = Vec2::new(1.0, -1.0);
+    const LEFT: Vec2 = Vec2::new(-1.0, 0.0);
+    const RIGHT: Vec2 = Vec2::new(1.0, 0.0);
+    match value {
+        1 => &[CENTER],
+        2 => &[LOW, HIGH],
+        3 => &[LOW, CENTER, HIGH],
+        4 => &[LOW, RIGHT_LOW, LEFT_HIGH, HIGH],
+        5 => &[LOW, RIGHT_LOW, CENTER, LEFT_HIGH, HIGH],
+        6 => &[LOW, LEFT, LEFT_HIGH, RIGHT_LOW, RIGHT, HIGH],
+        _ => &[],
+    }
+}
+
+/// The strokes of a number, as rectangles in a box one unit high centered on the origin,
+/// like the segments of a digital display. Sixes and nines are underlined, so that they
+/// can be told apart upside down.
+fn number_strokes(value: u32) -> Vec<Rect> {
+    const A: u8 = 1 << 0;
+    const B: u8 = 1 << 1;
+    const C: u8 = 1 << 2;
+    const D: u8 = 1 << 3;
+    const E: u8 = 1 << 4;
+    const F: u8 = 1 << 5;
+    const G: u8 = 1 << 6;
+    const DIGITS: [u8; 10] = [
+        A | B | C | D | E | F,
+        B | C,
+        A | B | D | E | G,
+        A | B | C | D | G,
+        B | C | F | G,
+        A | C | D | F | G,
+        A | C | D | E | F | G,
+        A | B | C,
+        A | B | C | D | E | F | G,
+        A | B | C | D | F | G,
+    ];
+
+    let digits: Vec<u32> = value
+        .to_string()
+        .chars()
+        .filter_map(|digit| digit.to_digit(10))
+        .collect();
+    let width = digits.len() as f32 * (DIGIT_WIDTH + DIGIT_SPACING) - DIGIT_SPACING;
+    let (half_width, stroke) = (DIGIT_WIDTH / 2.0, DIGIT_STROKE);
+    let segments = [
+        (
+            A,
+            Vec2::new(0.0, 0.5 - stroke / 2.0),
+            Vec2::new(DIGIT_WIDTH, stroke),
+        ),
+        (
+            B,
+            Vec2::new(half_width - stroke / 2.0, 0.25),
+            Vec2::new(stroke, 0.5),
+        ),
+        (
+            C,
+            Vec2::new(half_width - stroke / 2.0, -0.25),
+            Vec2::new(stroke, 0.5),
+        ),
+        (
+            D,
+            Vec2::new(0.0, stroke / 2.0 - 0.5),
+            Vec2::new(DIGIT_WIDTH, stroke),
+        ),
+        (
+            E,
+            Vec2::new(stroke / 2.0 - half_width, -0.25),
+            Vec2::new(stroke, 0.5),
+        ),
+        (
+            F,
+            Vec2::new(stroke / 2.0 - half_width, 0.25),
+            Vec2::new(stroke, 0.5),
+        ),
+        (G, Vec2::ZERO, Vec2::new(DIGIT_WIDTH, stroke)),
+    ];
+
+    let mut strokes = Vec::new();
+    for (i, &digit) in digits.iter().enumerate() {
+        let x = i as f32 * (DIGIT_WIDTH + DIGIT_SPACING) + half_width - width / 2.0;
+        for &(segment, center, size) in &segments {
+            if DIGITS[digit as usize] & segment != 0 {
+                strokes.push(Rect::from_center_size(center + Vec2::X * x, size));
+            }
+        }
+    }
+    if value == 6 || value == 9 {
+        strokes.push(Rect::from_center_size(
+            Vec2::new(0.0, -0.5 - 2.0 * stroke),
+            Vec2::new(width, stroke),
+        ));
+    }
+    strokes
+}
+
+/// A flat mesh of a number facing +Z, one unit high.
+fn number_mesh(value: u32) -> Mesh {
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    for stroke in number_strokes(value) {
+        let first = positions.len() as u32;
+        positions.extend([
+            stroke.min.extend(0.0),
+            Vec3::new(stroke.max.x, stroke.min.y, 0.0),
+            stroke.max.extend(0.0),
+            Vec3::new(stroke.min.x, stroke.max.y, 0.0),
+        ]);
+        indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+    let normals = vec![Vec3::Z; positions.len()];
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+/// Paints the meshes of a die's model with the die's skin once the model has spawned
+fn paint_die_model(
+    trigger: Trigger<SceneInstanceReady>,
+    mut commands: Commands,
+    models: Query<&ChildOf, With<DieModel>>,
+    dice: Query<&MeshMaterial3d<StandardMaterial>, With<Die>>,
+    children: Query<&Children>,
+    model_meshes: Query<(), With<Mesh3d>>,
+) {
+    let model = trigger.target();
+    let Ok(child_of) = models.get(model) else {
+        return;
+    };
+    let Ok(body) = dice.get(child_of.parent()) else {
+        return;
+    };
+    for descendant in children.iter_descendants(model) {
+        if model_meshes.contains(descendant) {
+            commands.entity(descendant).insert(body.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_skins_parse() {
+        for skin in [
+            include_str!("../../../../assets/skins/ivory.skin.ron"),
+            include_str!("../../../../assets/skins/ruby.skin.ron"),
+            include_str!("../../../../assets/skins/jade.skin.ron"),
+            include_str!("../../../../assets/skins/onyx.skin.ron"),
+        ] {
+            let skin: DieSkinFile = ron::de::from_str(skin).unwrap();
+            assert!(!skin.name.is_empty());
+        }
+    }
+
+    #[test]
+    fn d6_markings_fit_the_model() {
+        let half_size = 0.119;
+        let bounds = (Vec3::splat(-half_size), Vec3::splat(half_size));
+        for decal in DieKind::D6.face_decals() {
+            let fitted = fit_to_model(decal, bounds);
+            assert!(
+                (fitted.center - decal.normal * half_size).length() < 1e-5,
+                "{fitted:?}"
+            );
+            assert!((fitted.radius - half_size).abs() < 1e-5, "{fitted:?}");
+        }
+    }
+
+    #[test]
+    fn every_d6_face_has_as_many_pips_as_its_value() {
+        for value in 1..=6 {
+            assert_eq!(pip_layout(value).len(), value as usize);
+        }
+    }
+
+    #[test]
+    fn numbers_fit_on_their_face() {
+        assert_eq!(number_strokes(1).len(), 2);
+        assert_eq!(number_strokes(8).len(), 7);
+        // Underlined.
+        assert_eq!(number_strokes(6).len(), 7);
+        assert_eq!(number_strokes(18).len(), 9);
+        for value in 1..=20 {
+            for stroke in number_strokes(value) {
+                // The face is a circle of radius 1 / NUMBER_SIZE around the number.
+                for corner in [stroke.min, stroke.max, stroke.min.with_x(stroke.max.x)] {
+                    assert!(corner.length() * NUMBER_SIZE < 1.0, "{value}: {stroke:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dice_fall_back_to_the_skin_of_their_seat() {
+        let mut choice = DieSkinChoice {
+            seats: vec![3, 2],
+            seat: 1,
+            dice: vec![None, Some(1)],
+        };
+        assert_eq!(choice.skin_of(0), 2);
+        assert_eq!(choice.skin_of(1), 1);
+        assert_eq!(choice.skin_of(5), 2);
+
+        choice.seat = 0;
+        assert_eq!(choice.skin_of(0), 3);
+        choice.seat = 4;
+        assert_eq!(choice.skin_of(0), 0);
+    }
+}
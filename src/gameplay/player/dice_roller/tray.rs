@@ -0,0 +1,264 @@
+//! The tray the dice are rolled in, generated from a [`DiceTray`] description.
+
+use std::f32::consts::{PI, TAU};
+
+use avian3d::prelude::*;
+use bevy::prelude::*;
+
+use crate::screens::Screen;
+
+pub(super) fn plugin(app: &mut App) {
+    app.register_type::<DiceTray>();
+
+    app.add_systems(OnEnter(Screen::Gameplay), spawn_dice_tray);
+    app.add_systems(Update, build_dice_trays);
+}
+
+/// A tray with walls and an invisible lid that keeps the dice from rolling away.
+///
+/// The walls, floor and lid are generated as children of the tray, and rebuilt whenever
+/// the description changes.
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Component)]
+pub struct DiceTray {
+    /// Inner width of the tray, measured between opposite walls.
+    pub size: f32,
+    /// Height of the walls, and of the lid above the floor.
+    pub wall_height: f32,
+    pub wall_thickness: f32,
+    pub floor: TrayFloor,
+    pub footprint: TrayFootprint,
+}
+
+impl Default for DiceTray {
+    fn default() -> Self {
+        Self {
+            size: 3.0,
+            wall_height: 6.0,
+            wall_thickness: 0.1,
+            floor: TrayFloor::DiceMat,
+            footprint: TrayFootprint::Square,
+        }
+    }
+}
+
+/// What the floor of a [`DiceTray`] is covered with.
+#[derive(Reflect, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TrayFloor {
+    /// A hard, slippery surface that the dice bounce and slide on.
+    Wood,
+    /// A felt mat that grips the dice so that they settle quickly.
+    #[default]
+    DiceMat,
+}
+
+impl TrayFloor {
+    fn color(self) -> Color {
+        match self {
+            TrayFloor::Wood => Color::srgb(0.55, 0.36, 0.2),
+            TrayFloor::DiceMat => Color::srgb(0.1, 0.35, 0.2),
+        }
+    }
+
+    fn friction(self) -> Friction {
+        match self {
+            TrayFloor::Wood => Friction::new(0.3),
+            TrayFloor::DiceMat => Friction::new(0.9),
+        }
+    }
+
+    fn restitution(self) -> Restitution {
+        match self {
+            TrayFloor::Wood => Restitution::new(0.4),
+            TrayFloor::DiceMat => Restitution::new(0.1),
+        }
+    }
+}
+
+/// The shape of a [`DiceTray`] seen from above.
+#[derive(Reflect, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TrayFootprint {
+    #[default]
+    Square,
+    Octagon,
+    /// A circle approximated by the given number of straight walls.
+    Rounded {
+        segments: u32,
+    },
+}
+
+impl TrayFootprint {
+    fn sides(self) -> u32 {
+        match self {
+            TrayFootprint::Square => 4,
+            TrayFootprint::Octagon => 8,
+            TrayFootprint::Rounded { segments } => segments.max(3),
+        }
+    }
+}
+
+/// A single wall of a tray.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Wall {
+    transform: Transform,
+    /// Full length, height and thickness of the wall.
+    size: Vec3,
+}
+
+impl DiceTray {
+    /// The walls around the footprint, each facing the center of the tray.
+    fn walls(&self) -> Vec<Wall> {
+        let sides = self.footprint.sides();
+        let apothem = self.size / 2.0;
+        // Lengthen the walls so that they overlap at the corners instead of leaving gaps.
+        let length = 2.0 * (apothem + self.wall_thickness) * (PI / sides as f32).tan();
+        let distance = apothem + self.wall_thickness / 2.0;
+
+        (0..sides)
+            .map(|i| {
+                let angle = TAU * i as f32 / sides as f32;
+                let normal = Vec3::new(angle.cos(), 0.0, angle.sin());
+                let translation = normal * distance + Vec3::Y * self.wall_height / 2.0;
+                Wall {
+                    transform: Transform::from_translation(translation).looking_to(normal, Vec3::Y),
+                    size: Vec3::new(length, self.wall_height, self.wall_thickness),
+                }
+            })
+            .collect()
+    }
+
+    /// Width of the square floor and lid that cover the whole footprint.
+    fn outer_width(&self) -> f32 {
+        let sides = self.footprint.sides();
+        2.0 * (self.size / 2.0 + self.wall_thickness) / (PI / sides as f32).cos()
+    }
+
+    /// Moves a point, relative to the center of the tray, horizontally back inside the
+    /// circle that fits within the walls, keeping the given distance from them.
+    pub(super) fn clamp_inside(&self, point: Vec3, margin: f32) -> Vec3 {
+        let radius = (self.size / 2.0 - margin).max(0.0);
+        let horizontal = point.xz().clamp_length_max(radius);
+        Vec3::new(horizontal.x, point.y, horizontal.y)
+    }
+}
+
+/// Spawns the tray the dice are rolled in
+fn spawn_dice_tray(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Dice Tray"),
+        DiceTray::default(),
+        Transform::default(),
+        Visibility::default(),
+        RigidBody::Static,
+        StateScoped(Screen::Gameplay),
+    ));
+}
+
+/// Generates the walls, floor and lid of trays that were added or changed
+fn build_dice_trays(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    trays: Query<(Entity, &DiceTray), Changed<DiceTray>>,
+) {
+    for (entity, tray) in &trays {
+        let wall_material = materials.add(StandardMaterial {
+            base_color: Color::srgba(0.0, 0.5, 0.5, 0.0),
+            alpha_mode: AlphaMode::Blend,
+            ..Default::default()
+        });
+        let floor_material = materials.add(StandardMaterial {
+            base_color: tray.floor.color(),
+            perceptual_roughness: 1.0,
+            ..Default::default()
+        });
+        let width = tray.outer_width();
+        let thickness = tray.wall_thickness;
+
+        commands
+            .entity(entity)
+            .despawn_related::<Children>()
+            .with_children(|parent| {
+                for wall in tray.walls() {
+                    parent.spawn((
+                        Name::new("Tray Wall"),
+                        wall.transform,
+                        Mesh3d(meshes.add(Cuboid::from_size(wall.size))),
+                        MeshMaterial3d(wall_material.clone()),
+                        Collider::cuboid(wall.size.x, wall.size.y, wall.size.z),
+                        // Let clicks through to the ground, to place buildings.
+                        Pickable::IGNORE,
+                    ));
+                }
+
+                parent.spawn((
+                    Name::new("Tray Floor"),
+                    Transform::from_xyz(0.0, -thickness / 2.0, 0.0),
+                    Mesh3d(meshes.add(Cuboid::new(width, thickness, width))),
+                    MeshMaterial3d(floor_material),
+                    Collider::cuboid(width, thickness, width),
+                    Pickable::IGNORE,
+                    tray.floor.friction(),
+                    tray.floor.restitution(),
+                ));
+
+                parent.spawn((
+                    Name::new("Tray Lid"),
+                    Transform::from_xyz(0.0, tray.wall_height + thickness / 2.0, 0.0),
+                    Collider::cuboid(width, thickness, width),
+                ));
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_walls_enclose_the_inner_size() {
+        let tray = DiceTray::default();
+        let walls = tray.walls();
+        assert_eq!(walls.len(), 4);
+        for wall in walls {
+            let inner_face = wall.transform.translation.xz().length() - tray.wall_thickness / 2.0;
+            assert!((inner_face - tray.size / 2.0).abs() < 1e-5, "{wall:?}");
+            // The wall spans the whole side, corners included.
+            assert!(wall.size.x >= tray.size + tray.wall_thickness, "{wall:?}");
+        }
+    }
+
+    #[test]
+    fn clamps_points_inside_the_walls() {
+        let tray = DiceTray::default();
+        let inside = Vec3::new(0.5, 2.0, -0.5);
+        assert_eq!(tray.clamp_inside(inside, 0.4), inside);
+
+        let clamped = tray.clamp_inside(Vec3::new(10.0, 2.0, 0.0), 0.4);
+        assert!(
+            (clamped - Vec3::new(1.1, 2.0, 0.0)).length() < 1e-5,
+            "{clamped}"
+        );
+    }
+
+    #[test]
+    fn walls_face_the_center() {
+        for footprint in [
+            TrayFootprint::Square,
+            TrayFootprint::Octagon,
+            TrayFootprint::Rounded { segments: 24 },
+        ] {
+            let tray = DiceTray {
+                footprint,
+                ..Default::default()
+            };
+            let walls = tray.walls();
+            assert_eq!(walls.len() as u32, footprint.sides());
+            for wall in walls {
+                let outward = wall.transform.translation.with_y(0.0).normalize();
+                let thickness_axis = wall.transform.rotation * Vec3::Z;
+                assert!(thickness_axis.dot(outward).abs() > 0.999, "{footprint:?}");
+            }
+        }
+    }
+}
@@ -1,199 +1,471 @@
-// use crate::{
-//     gameplay::models::suburban::{BuildingType, CurrentBuilding, SuburbanBuildings},
-//     screens::Screen,
-//     theme::{
-//         palette::{
-//             BUTTON_BACKGROUND, BUTTON_HOVERED_BACKGROUND, BUTTON_PRESSED_BACKGROUND, BUTTON_TEXT,
-//         },
-//         prelude::InteractionPalette,
-//     },
-// };
-// use bevy::{ecs::schedule::NotSystem, input::common_conditions::input_just_pressed, prelude::*};
-// use bevy_rts_camera::RtsCamera;
-// 
-//
-// pub(crate) fn plugin(app: &mut App) {
-//     app.add_systems(OnEnter(Screen::Gameplay), render_ui);
-//     app.add_systems(
-//         Update,
-//         (select_building_on_click)
-//             .run_if(in_state(Screen::Gameplay).and(input_just_pressed(MouseButton::Left))),
-//     );
-//
-//     app.add_systems(
-//         Update,
-//         (preview_building)
-//             .run_if(in_state(Screen::Gameplay).and(resource_exists::<CurrentBuilding>)),
-//     );
-//
-//     app.add_systems(
-//         Update,
-//         (place_building_on_click).run_if(
-//             in_state(Screen::Gameplay)
-//                 .and(resource_exists::<CurrentBuilding>)
-//                 .and(
-//                     input_just_pressed(MouseButton::Left)
-//                         .and(any_with_component::<PreviewBuilding>),
-//                 ),
-//         ),
-//     );
-//
-//     app.add_systems(
-//         Update,
-//         cleanup_current_building.run_if(resource_removed::<CurrentBuilding>),
-//     );
-//
-//     app.add_systems(
-//         Update,
-//         (clear_building_selection).run_if(
-//             in_state(Screen::Gameplay)
-//                 .and(input_just_pressed(MouseButton::Right))
-//                 .and(any_with_component::<PreviewBuilding>),
-//         ),
-//     );
-//
-//     app.add_systems(OnExit(Screen::Gameplay), cleanup_ui);
-// }
-//
-// #[derive(Component)]
-// struct HotbarUi;
-//
-// 
-// fn render_ui(mut commands: Commands, buildings: Res<SuburbanBuildings>) {
-//     commands
-//         .spawn((
-//             Node {
-//                 width: Val::Percent(100.0),
-//                 height: Val::Percent(100.0),
-//                 justify_content: JustifyContent::Center,
-//                 align_items: AlignItems::End,
-//                 flex_direction: FlexDirection::Row,
-//                 ..default()
-//             },
-//             HotbarUi,
-//         )
-//         .with_children(|parent| {
-//             for (btype, handle) in buildings.buildings.iter() {
-//                 parent.spawn((
-//                     Button,
-//                     Node {
-//                         width: Val::Px(50.0),
-//                         height: Val::Px(50.0),
-//                         justify_content: JustifyContent::Center,
-//                         align_items: AlignItems::Center,
-//                         ..default()
-//                     },
-//                     Name::new(handle.name().to_string()),
-//                     btype.clone(),
-//                     BackgroundColor(BUTTON_BACKGROUND),
-//                     InteractionPalette {
-//                         none: BUTTON_BACKGROUND,
-//                         hovered: BUTTON_HOVERED_BACKGROUND,
-//                         pressed: BUTTON_PRESSED_BACKGROUND,
-//                     },
-//                     children![(
-//                         Name::new("Button Text"),
-//                         Text(handle.name().to_string()),
-//                         TextFont::from_font_size(40.0),
-//                         TextColor(BUTTON_TEXT),
-//                         // Don't bubble picking events from the text up to the button.
-//                         Pickable::IGNORE,
-//                     )],
-//                 ));
-//             }
-//         });
-// }
-//
-// 
-// fn cleanup_ui(mut commands: Commands, ui: Single<Entity, With<HotbarUi>>) {
-//     commands.entity(*ui).despawn()
-// }
-//
-// 
-// fn select_building_on_click(
-//     mut commands: Commands,
-//     mut interaction_q: Query<(&Interaction, &BuildingType), (Changed<Interaction>, With<Button>)>,
-//     buildings: Res<SuburbanBuildings>,
-//     gltf: Res<Assets<Gltf>>,
-//     mut materials: ResMut<Assets<StandardMaterial>>,
-// ) {
-//     for (interaction, building_type) in interaction_q.iter_mut() {
-//         if *interaction == Interaction::Pressed {
-//             match CurrentBuilding::new(building_type, &buildings, &gltf, &mut materials) {
-//                 Ok(current_building) => {
-//                     commands.insert_resource(current_building);
-//                     info!("Selected {:?}", building_type);
-//                 }
-//                 Err(e) => {
-//                     error!("Couldn't construct current_building resource.{e:?}");
-//                 }
-//             }
-//         }
-//     }
-// }
-//
-// 
-// fn cleanup_current_building(
-//     mut commands: Commands,
-//     mut preview_q: Query<Entity, With<PreviewBuilding>>,
-// ) {
-//     for entity in preview_q.iter_mut() {
-//         commands.entity(entity).despawn();
-//     }
-// }
-//
-// 
-// fn preview_building(
-//     mut commands: Commands,
-//     mut preview_q: Query<Entity, With<PreviewBuilding>>,
-//     window: Single<&Window>,
-//     camera: Single<(&Camera, &GlobalTransform), With<RtsCamera>>,
-// ) {
-//     // Compute raycast from mouse to world
-//     if let Some(cursor_pos) = window.cursor_position() {
-//         let (camera, cam_transform) = camera.into_inner();
-//         if let Ok(ray) = camera.viewport_to_world(cam_transform, cursor_pos) {
-//             // Find ground intersection (assume y=0)
-//             let t = -ray.origin.y / ray.direction.y;
-//             let world_pos = ray.origin + ray.direction * t;
-//
-//             // Spawn preview if it doesn't exist
-//             if preview_q.is_empty() {
-//                 commands.spawn((Transform::from_translation(world_pos), PreviewBuilding));
-//             } else {
-//                 // Move existing preview
-//                 for entity in preview_q.iter_mut() {
-//                     commands
-//                         .entity(entity)
-//                         .insert(Transform::from_translation(world_pos));
-//                 }
-//             }
-//         }
-//     }
-// }
-//
-// #[derive(Component)]
-// struct PreviewBuilding;
-//
-// 
-// fn place_building_on_click(
-//     preview_q: Single<&Transform, With<PreviewBuilding>>,
-//     current_building: Res<CurrentBuilding>,
-//     mut materials: ResMut<Assets<StandardMaterial>>,
-//     mut commands: Commands,
-// ) {
-//     let tf = *preview_q;
-//     let id = current_building.mat_id();
-//     if let Some(mat_asset) = materials.get_mut(id) {
-//         mat_asset.diffuse_transmission = 1.0;
-//     } else {
-//         warn!("Invalid material asset with id {id:?}");
-//     }
-//
-//     commands.spawn((SceneRoot(current_building.scene().clone()), *tf));
-// }
-//
-// 
-// fn clear_building_selection(mut commands: Commands) {
-//     commands.remove_resource::<CurrentBuilding>();
-// }
+//! Placing buildings in the town.
+//!
+//! The hotbar lists every building. Picking one shows a see-through preview of it under
+//! the cursor, snapped to the town grid and tinted by whether it fits there. The preview
+//! is turned with [`TURN_LEFT_KEY`], [`TURN_RIGHT_KEY`] or the mouse wheel, and mirrored
+//! with [`MIRROR_KEY`]. Clicking the ground places it, and right-clicking cancels.
+
+use avian3d::prelude::*;
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    scene::SceneInstanceReady,
+    window::PrimaryWindow,
+};
+use bevy_egui::EguiContexts;
+
+use crate::{
+    PausableSystems,
+    gameplay::{
+        level::town_grid::{PlacementError, TileRect, TownGrid},
+        models::{
+            bounds::BuildingBounds,
+            suburban::{BuildingCatalog, BuildingId, BuildingOrientation, CurrentBuilding},
+        },
+        player::Player,
+    },
+    menus::Menu,
+    screens::Screen,
+    theme::{
+        palette::{
+            BUTTON_BACKGROUND, BUTTON_HOVERED_BACKGROUND, BUTTON_PRESSED_BACKGROUND, BUTTON_TEXT,
+        },
+        prelude::InteractionPalette,
+    },
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::Gameplay), spawn_hotbar);
+    app.add_systems(OnExit(Screen::Gameplay), clear_building_selection);
+
+    app.add_observer(place_building);
+    app.add_observer(cancel_building);
+    app.add_observer(ghost_preview);
+    app.add_observer(unpickable_building);
+    app.add_systems(
+        Update,
+        (
+            despawn_preview.run_if(resource_removed::<CurrentBuilding>),
+            spawn_preview.run_if(resource_exists_and_changed::<CurrentBuilding>),
+            orient_building.run_if(resource_exists::<CurrentBuilding>),
+            move_preview.run_if(resource_exists::<CurrentBuilding>),
+            tint_preview,
+        )
+            .chain()
+            .in_set(PausableSystems)
+            .run_if(in_state(Screen::Gameplay)),
+    );
+}
+
+const TURN_LEFT_KEY: KeyCode = KeyCode::KeyQ;
+const TURN_RIGHT_KEY: KeyCode = KeyCode::KeyE;
+const MIRROR_KEY: KeyCode = KeyCode::KeyM;
+/// How far a touchpad scrolls to turn the building by a quarter turn.
+const PIXELS_PER_TURN: f32 = 100.0;
+
+/// How opaque the preview of a building is.
+const PREVIEW_ALPHA: f32 = 0.5;
+/// Glow of a preview that can be placed where it is.
+const FITS_TINT: LinearRgba = LinearRgba::rgb(0.0, 0.4, 0.0);
+/// Glow of a preview that overlaps with another building.
+const OVERLAPS_TINT: LinearRgba = LinearRgba::rgb(0.8, 0.0, 0.0);
+/// Glow of a preview that sticks out of the town.
+const OUT_OF_BOUNDS_TINT: LinearRgba = LinearRgba::rgb(0.8, 0.5, 0.0);
+
+/// The see-through building following the cursor until it is placed.
+#[derive(Component, Debug, Default, PartialEq)]
+struct PreviewBuilding {
+    /// The tiles the building would be placed on, or why it can't be, if the cursor is over
+    /// the ground.
+    placement: Option<Result<TileRect, PlacementError>>,
+}
+
+impl PreviewBuilding {
+    fn tint(&self) -> LinearRgba {
+        match self.placement {
+            None | Some(Ok(_)) => FITS_TINT,
+            Some(Err(PlacementError::Overlaps { .. })) => OVERLAPS_TINT,
+            Some(Err(PlacementError::OutOfBounds)) => OUT_OF_BOUNDS_TINT,
+        }
+    }
+}
+
+/// The model of a placed building.
+#[derive(Component, Debug)]
+struct BuildingModel;
+
+/// The see-through materials of a preview, to tint it.
+#[derive(Component, Debug)]
+struct GhostMaterials(Vec<Handle<StandardMaterial>>);
+
+fn spawn_hotbar(mut commands: Commands, catalog: Res<BuildingCatalog>) {
+    commands
+        .spawn((
+            Name::new("Hotbar"),
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_content: AlignContent::End,
+                flex_wrap: FlexWrap::Wrap,
+                column_gap: Val::Px(4.0),
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            // Don't block picking events for the world behind the hotbar.
+            Pickable::IGNORE,
+            StateScoped(Screen::Gameplay),
+        ))
+        .with_children(|parent| {
+            for building in catalog.iter() {
+                parent
+                    .spawn((
+                        Button,
+                        Node {
+                            padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        Name::new(building.name.clone()),
+                        building.id.clone(),
+                        BackgroundColor(BUTTON_BACKGROUND),
+                        InteractionPalette {
+                            none: BUTTON_BACKGROUND,
+                            hovered: BUTTON_HOVERED_BACKGROUND,
+                            pressed: BUTTON_PRESSED_BACKGROUND,
+                        },
+                        children![(
+                            Name::new("Button Text"),
+                            Text(building.name.clone()),
+                            TextFont::from_font_size(16.0),
+                            TextColor(BUTTON_TEXT),
+                            // Don't bubble picking events from the text up to the button.
+                            Pickable::IGNORE,
+                        )],
+                    ))
+                    .observe(select_building);
+            }
+        });
+}
+
+/// Picks the building of a hotbar button, replacing the one being placed
+fn select_building(
+    mut trigger: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    building_ids: Query<&BuildingId, With<Button>>,
+    catalog: Res<BuildingCatalog>,
+    gltf: Res<Assets<Gltf>>,
+    previous: Option<Res<CurrentBuilding>>,
+) {
+    if trigger.event().button != PointerButton::Primary {
+        return;
+    }
+    trigger.propagate(false);
+    let Ok(id) = building_ids.get(trigger.target()) else {
+        return;
+    };
+    match CurrentBuilding::new(id, &catalog, &gltf) {
+        Ok(mut current_building) => {
+            info!("Selected {}", current_building.name());
+            // Keep turning the buildings the same way, to line them up.
+            if let Some(previous) = previous {
+                current_building.set_orientation(previous.orientation());
+            }
+            commands.insert_resource(current_building);
+        }
+        Err(e) => {
+            error!("Couldn't construct current_building resource. {e}");
+        }
+    }
+}
+
+/// Spawns a fresh preview when another building is picked, replacing the previous one
+fn spawn_preview(
+    mut commands: Commands,
+    current_building: Res<CurrentBuilding>,
+    previews: Query<(Entity, &SceneRoot), With<PreviewBuilding>>,
+) {
+    // Turning the building changes the resource too, which keeps the preview.
+    if previews
+        .iter()
+        .any(|(_, scene)| scene.0 == *current_building.scene())
+    {
+        return;
+    }
+    for (entity, _) in &previews {
+        commands.entity(entity).despawn();
+    }
+    commands.spawn((
+        Name::new("Preview Building"),
+        PreviewBuilding::default(),
+        SceneRoot(current_building.scene().clone()),
+        // Hidden until the cursor is over the ground.
+        Visibility::Hidden,
+        StateScoped(Screen::Gameplay),
+    ));
+}
+
+/// Makes the preview see-through, and keeps it from being picked instead of the ground
+fn ghost_preview(
+    trigger: Trigger<SceneInstanceReady>,
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    previews: Query<(), With<PreviewBuilding>>,
+    children: Query<&Children>,
+    meshes: Query<&MeshMaterial3d<StandardMaterial>>,
+) {
+    let preview = trigger.target();
+    if !previews.contains(preview) {
+        return;
+    }
+    let mut ghosts = Vec::new();
+    for descendant in children.iter_descendants(preview) {
+        let mut entity = commands.entity(descendant);
+        entity.insert(Pickable::IGNORE);
+        let Some(material) = meshes
+            .get(descendant)
+            .ok()
+            .and_then(|material| materials.get(&material.0))
+        else {
+            continue;
+        };
+        // The materials are shared with the placed buildings, so the preview gets its own.
+        let mut ghost = material.clone();
+        ghost.base_color.set_alpha(PREVIEW_ALPHA);
+        ghost.alpha_mode = AlphaMode::Blend;
+        let ghost = materials.add(ghost);
+        entity.insert(MeshMaterial3d(ghost.clone()));
+        ghosts.push(ghost);
+    }
+    commands.entity(preview).insert(GhostMaterials(ghosts));
+}
+
+/// Keeps a placed building from catching the clicks meant for the ground around it
+fn unpickable_building(
+    trigger: Trigger<SceneInstanceReady>,
+    mut commands: Commands,
+    models: Query<(), With<BuildingModel>>,
+    children: Query<&Children>,
+) {
+    let model = trigger.target();
+    if !models.contains(model) {
+        return;
+    }
+    for descendant in children.iter_descendants(model) {
+        commands.entity(descendant).insert(Pickable::IGNORE);
+    }
+}
+
+/// Turns the current building with the keyboard or the mouse wheel, and mirrors it
+fn orient_building(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut wheel: EventReader<MouseWheel>,
+    mut scrolled_pixels: Local<f32>,
+    mut current_building: ResMut<CurrentBuilding>,
+) {
+    let mut quarter_turns =
+        keys.just_pressed(TURN_LEFT_KEY) as i32 - keys.just_pressed(TURN_RIGHT_KEY) as i32;
+    for event in wheel.read() {
+        match event.unit {
+            MouseScrollUnit::Line => {
+                quarter_turns += (event.y > 0.0) as i32 - (event.y < 0.0) as i32;
+            }
+            MouseScrollUnit::Pixel => {
+                *scrolled_pixels += event.y;
+                let turns = (*scrolled_pixels / PIXELS_PER_TURN).trunc();
+                *scrolled_pixels -= turns * PIXELS_PER_TURN;
+                quarter_turns += turns as i32;
+            }
+        }
+    }
+    let mut orientation = current_building.orientation().turned(quarter_turns);
+    if keys.just_pressed(MIRROR_KEY) {
+        orientation = orientation.flipped();
+    }
+    if orientation != current_building.orientation() {
+        current_building.set_orientation(orientation);
+    }
+}
+
+/// Snaps the preview to the tiles under the cursor, hiding it when the cursor isn't over
+/// the ground
+fn move_preview(
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform), With<Player>>,
+    grid: Res<TownGrid>,
+    current_building: Res<CurrentBuilding>,
+    preview: Single<(&mut PreviewBuilding, &mut Transform, &mut Visibility)>,
+) {
+    let (camera, camera_transform) = *camera;
+    let (mut preview, mut transform, mut visibility) = preview.into_inner();
+    let ground = window
+        .cursor_position()
+        .and_then(|cursor| ground_under(camera, camera_transform, cursor));
+    let Some(ground) = ground else {
+        preview.set_if_neq(PreviewBuilding::default());
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let orientation = current_building.orientation();
+    let tiles = grid.snap(ground, current_building.footprint());
+    preview.set_if_neq(PreviewBuilding {
+        placement: Some(grid.check(tiles).map(|()| tiles)),
+    });
+    *transform = Transform {
+        translation: building_origin(&grid, tiles, current_building.bounds(), orientation),
+        rotation: orientation.rotation(),
+        scale: orientation.scale(),
+    };
+    *visibility = Visibility::Inherited;
+}
+
+/// Tints the preview by whether it can be placed where it is
+fn tint_preview(
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    previews: Query<
+        (&PreviewBuilding, &GhostMaterials),
+        Or<(Changed<PreviewBuilding>, Added<GhostMaterials>)>,
+    >,
+) {
+    for (preview, ghosts) in &previews {
+        for ghost in &ghosts.0 {
+            if let Some(material) = materials.get_mut(ghost) {
+                material.emissive = preview.tint();
+            }
+        }
+    }
+}
+
+/// Where to put a building so that its model is centered on the given tiles.
+fn building_origin(
+    grid: &TownGrid,
+    tiles: TileRect,
+    bounds: &BuildingBounds,
+    orientation: BuildingOrientation,
+) -> Vec3 {
+    let center = orientation.apply(bounds.center());
+    grid.center(tiles) - Vec3::new(center.x, 0.0, center.z)
+}
+
+/// The point of the ground, at y = 0, seen at the given viewport position.
+fn ground_under(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    viewport_position: Vec2,
+) -> Option<Vec3> {
+    let ray = camera
+        .viewport_to_world(camera_transform, viewport_position)
+        .ok()?;
+    let distance = ray.intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Y))?;
+    Some(ray.get_point(distance))
+}
+
+/// Whether a click landed on the ground.
+///
+/// Clicks that hit nothing else, neither UI nor dice, land on the window. The tray and the
+/// placed buildings let clicks through, and the preview is placed on the ground under them.
+/// Egui panels aren't entities, so clicks on them land on the window too and are left out.
+fn clicked_ground(
+    trigger: &Trigger<Pointer<Click>>,
+    windows: &Query<(), With<Window>>,
+    egui: &mut EguiContexts,
+) -> bool {
+    windows.contains(trigger.target())
+        && !egui.ctx_mut().is_ok_and(|ctx| ctx.is_pointer_over_area())
+}
+
+/// Places the current building where the preview is, when the ground is clicked and the
+/// building fits there.
+fn place_building(
+    trigger: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    menu: Res<State<Menu>>,
+    mut grid: ResMut<TownGrid>,
+    windows: Query<(), With<Window>>,
+    mut egui: EguiContexts,
+    current_building: Option<Res<CurrentBuilding>>,
+    preview: Option<Single<&PreviewBuilding>>,
+) {
+    if trigger.event().button != PointerButton::Primary
+        || *menu.get() != Menu::None
+        || !clicked_ground(&trigger, &windows, &mut egui)
+    {
+        return;
+    }
+    let (Some(current_building), Some(preview)) = (current_building, preview) else {
+        return;
+    };
+    let tiles = match preview.placement {
+        Some(Ok(tiles)) => tiles,
+        Some(Err(e)) => {
+            info!("Can't place {} here. {e}", current_building.name());
+            return;
+        }
+        None => return,
+    };
+
+    let bounds = current_building.bounds();
+    let orientation = current_building.orientation();
+    let size = bounds.size();
+    let building = commands
+        .spawn((
+            Name::new(current_building.name().to_string()),
+            current_building.id().clone(),
+            orientation,
+            Transform::from_translation(building_origin(&grid, tiles, bounds, orientation))
+                .with_rotation(orientation.rotation()),
+            Visibility::default(),
+            StateScoped(Screen::Gameplay),
+            children![
+                (
+                    Name::new("Building Model"),
+                    BuildingModel,
+                    SceneRoot(current_building.scene().clone()),
+                    // Mirrored on its own, as the collider can't be.
+                    Transform::from_scale(orientation.scale()),
+                ),
+                (
+                    Name::new("Building Collider"),
+                    RigidBody::Static,
+                    Collider::cuboid(size.x, size.y, size.z),
+                    Transform::from_translation(orientation.scale() * bounds.center()),
+                ),
+            ],
+        ))
+        .id();
+    if let Err(e) = grid.occupy(tiles, building) {
+        warn!("Placed {} on taken tiles. {e}", current_building.name());
+    }
+}
+
+/// Drops the current building when the ground is right-clicked
+fn cancel_building(
+    trigger: Trigger<Pointer<Click>>,
+    mut commands: Commands,
+    menu: Res<State<Menu>>,
+    windows: Query<(), With<Window>>,
+    mut egui: EguiContexts,
+    current_building: Option<Res<CurrentBuilding>>,
+) {
+    if trigger.event().button == PointerButton::Secondary
+        && *menu.get() == Menu::None
+        && current_building.is_some()
+        && clicked_ground(&trigger, &windows, &mut egui)
+    {
+        commands.remove_resource::<CurrentBuilding>();
+    }
+}
+
+fn despawn_preview(mut commands: Commands, previews: Query<Entity, With<PreviewBuilding>>) {
+    for entity in &previews {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn clear_building_selection(mut commands: Commands) {
+    commands.remove_resource::<CurrentBuilding>();
+}
@@ -3,8 +3,10 @@ use bevy::prelude::*;
 mod dice_roller;
 mod interactables;
 
+pub use dice_roller::replay_roll_file;
+
 pub(super) fn plugin(app: &mut App) {
-    app.add_plugins(dice_roller::plugin);
+    app.add_plugins((dice_roller::plugin, interactables::plugin));
 }
 
 #[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
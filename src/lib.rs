@@ -20,6 +20,8 @@ use bevy::{
 };
 use bevy_egui::EguiPlugin;
 
+pub use gameplay::replay_roll_file;
+
 mod prelude {
     pub use super::*;
     pub use {
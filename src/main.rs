@@ -1,7 +1,19 @@
+use std::path::Path;
+
 use bevy::prelude::*;
 
-use hazard::AppPlugin;
+use hazard::{AppPlugin, replay_roll_file};
 
 fn main() -> AppExit {
+    // `--replay <file>` replays a recorded dice roll without opening a window.
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("--replay") {
+        let Some(path) = args.next() else {
+            eprintln!("Usage: hazard --replay <recording.ron>");
+            return AppExit::error();
+        };
+        return replay_roll_file(Path::new(&path));
+    }
+
     App::new().add_plugins(AppPlugin).run()
 }